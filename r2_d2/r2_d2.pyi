@@ -17,7 +17,55 @@ class R2Usage(Protocol):
     def __repr__(self) -> str: ...
 
 
+class R2Bucket(Protocol):
+    name: str
+    creation_date: str
+    location: Optional[str]
+    storage_class: Optional[str]
+
+    def __str__(self) -> str: ...
+
+    def __repr__(self) -> str: ...
+
+
+class R2Error(RuntimeError):
+    code: Optional[int]
+    """Cloudflare API error code"""
+    hint: Optional[str]
+    status: Optional[int]
+    """HTTP status of the API response"""
+
+
+class AuthenticationError(R2Error): ...
+
+
+class NotFound(R2Error): ...
+
+
+class BucketNotFound(NotFound): ...
+
+
+class BucketNotEmpty(R2Error): ...
+
+
+class RateLimited(R2Error): ...
+
+
+class ConfigError(R2Error): ...
+
+
+class RepositoryError(R2Error): ...
+
+
 async def usage() -> R2Usage: ...
 
 
+async def create_bucket(
+    name: str,
+    location_hint: Optional[str] = None,
+    jurisdiction: Optional[str] = None,
+    storage_class: Optional[str] = None,
+) -> R2Bucket: ...
+
+
 async def main_rs() -> int: ...
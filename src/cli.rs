@@ -1,5 +1,8 @@
+use crate::helpers::parse_duration;
+use crate::output::OutputFormat;
 use clap::Parser;
 use clap_complete::Shell;
+use std::path::PathBuf;
 
 pub const fn get_styles() -> clap::builder::Styles {
     clap::builder::Styles::styled()
@@ -49,6 +52,9 @@ pub struct Args {
     #[arg(long = "generate", value_enum)]
     pub generator: Option<Shell>,
 
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table, help = "Output format")]
+    pub format: OutputFormat,
+
     #[clap(subcommand)]
     pub cmd: Commands,
 }
@@ -58,17 +64,193 @@ pub struct AuthOptions {
     // add a boolean option to show full key:
     #[clap(short, long, help = "Show full key")]
     pub show: bool,
+
+    #[clap(
+        long,
+        help = "Check what the API token and S3 credentials are allowed to do"
+    )]
+    pub check: bool,
+
+    #[clap(short, long, help = "Bucket to run the `--check` against")]
+    pub bucket: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
-pub struct OverviewOptions {}
+pub struct OverviewOptions {
+    #[clap(
+        short,
+        long,
+        default_value = "name",
+        value_parser = ["name", "size", "objects", "uploads"],
+        help = "Sort by bucket name, or largest first by size, object count or pending uploads"
+    )]
+    pub sort: String,
+
+    #[clap(short, long, help = "Only show buckets whose name contains this text")]
+    pub filter: Option<String>,
+
+    #[clap(
+        long,
+        help = "Include every profile in R2_PROFILES_DIR (or every account of the API token), with subtotals per account"
+    )]
+    pub all_profiles: bool,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
-pub struct UploadOptions {}
+pub struct UploadOptions {
+    #[clap(help = "File to upload")]
+    pub file: String,
+
+    #[clap(short, long, help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
 pub struct InitOptions {}
 
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Parser)]
+pub struct BucketOptions {
+    #[clap(subcommand)]
+    pub cmd: BucketCommands,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct BucketInfoOptions {
+    #[clap(help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct BucketCreateOptions {
+    pub name: String,
+
+    #[clap(
+        long,
+        value_parser = ["apac", "eeur", "enam", "weur", "wnam", "oc"],
+        help = "Preferred region for the bucket"
+    )]
+    pub location_hint: Option<String>,
+
+    #[clap(
+        long,
+        value_parser = ["default", "eu", "fedramp"],
+        help = "Jurisdiction the data must stay in (defaults to `R2_JURISDICTION`)"
+    )]
+    pub jurisdiction: Option<String>,
+
+    #[clap(
+        long,
+        value_parser = ["Standard", "InfrequentAccess"],
+        help = "Default storage class for new objects"
+    )]
+    pub storage_class: Option<String>,
+
+    #[clap(long, help = "Enable public access via the r2.dev domain")]
+    pub public: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Parser)]
+pub struct CorsOptions {
+    #[clap(subcommand)]
+    pub cmd: CorsCommands,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct CorsGetOptions {
+    #[clap(help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct CorsSetOptions {
+    #[clap(help = "Policy file (.json or .toml)")]
+    pub policy: PathBuf,
+
+    #[clap(help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+
+    #[clap(long, help = "Only show the changes, don't apply them")]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct CorsDeleteOptions {
+    #[clap(help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Parser)]
+pub struct DomainOptions {
+    #[clap(subcommand)]
+    pub cmd: DomainCommands,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct DomainListOptions {
+    #[clap(help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct DomainAddOptions {
+    #[clap(help = "Custom domain, e.g. `assets.example.com`")]
+    pub domain: String,
+
+    #[clap(long, help = "Id of the Cloudflare zone the domain belongs to")]
+    pub zone_id: String,
+
+    #[clap(short, long, help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct DomainRemoveOptions {
+    #[clap(help = "Custom domain to detach")]
+    pub domain: String,
+
+    #[clap(short, long, help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct DomainManagedOptions {
+    #[clap(value_parser = ["enable", "disable"], help = "Enable or disable the r2.dev domain")]
+    pub action: String,
+
+    #[clap(short, long, help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct DoctorOptions {
+    #[clap(help = "Bucket to check (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct TempCredsOptions {
+    #[clap(short, long, help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+
+    #[clap(short, long, help = "Only allow access under this prefix (repeatable)")]
+    pub prefix: Vec<String>,
+
+    #[clap(
+        long,
+        default_value = "object-read-write",
+        value_parser = ["admin-read-write", "admin-read-only", "object-read-write", "object-read-only"],
+    )]
+    pub permission: String,
+
+    #[clap(
+        long,
+        default_value = "1h",
+        value_parser = parse_duration,
+        help = "How long the credentials stay valid (e.g. `1h`)"
+    )]
+    pub ttl: u64,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
 pub struct WipeOptions {
     #[clap(short, long, help = "Don't ask for confirmation")]
@@ -86,6 +268,173 @@ pub struct WipeOptions {
     pub include_bucket: bool,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Parser)]
+pub struct LifecycleOptions {
+    #[clap(subcommand)]
+    pub cmd: LifecycleCommands,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct LifecycleGetOptions {
+    #[clap(help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+#[clap(group(
+    clap::ArgGroup::new("action")
+        .required(true)
+        .multiple(true)
+        .args(["abort_multipart_after", "transition_ia_after", "expire_after"])
+))]
+pub struct LifecycleSetOptions {
+    #[clap(help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+
+    #[clap(
+        long,
+        help = "Name of the rule to add or update (default: based on the prefix)"
+    )]
+    pub id: Option<String>,
+
+    #[clap(
+        short,
+        long,
+        help = "Only apply to objects under this prefix (e.g. `data/`)"
+    )]
+    pub prefix: Option<String>,
+
+    #[clap(
+        long,
+        value_parser = parse_duration,
+        help = "Abort incomplete multipart uploads after this long (e.g. `7d`)"
+    )]
+    pub abort_multipart_after: Option<u64>,
+
+    #[clap(
+        long,
+        value_parser = parse_duration,
+        help = "Move objects to Infrequent Access after this long (e.g. `30d`)"
+    )]
+    pub transition_ia_after: Option<u64>,
+
+    #[clap(
+        long,
+        value_parser = parse_duration,
+        help = "Delete objects after this long (e.g. `365d`)"
+    )]
+    pub expire_after: Option<u64>,
+
+    #[clap(long, help = "Add the rule, but don't enable it yet")]
+    pub disabled: bool,
+
+    #[clap(long, help = "Only show the changes, don't apply them")]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Parser)]
+pub struct LockRulesOptions {
+    #[clap(subcommand)]
+    pub cmd: LockRulesCommands,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct LockRulesListOptions {
+    #[clap(short, long, help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct LockRulesAddOptions {
+    #[clap(help = "Unique name of the rule")]
+    pub id: String,
+
+    #[clap(short, long, help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+
+    #[clap(
+        short,
+        long,
+        help = "Only lock objects under this prefix (e.g. `data/`)"
+    )]
+    pub prefix: Option<String>,
+
+    #[clap(
+        long,
+        value_parser = parse_duration,
+        conflicts_with = "date",
+        help = "Retain objects for this long after upload (e.g. `30d`)"
+    )]
+    pub max_age: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Retain objects until this date (e.g. `2030-01-01T00:00:00Z`)"
+    )]
+    pub date: Option<String>,
+
+    #[clap(long, help = "Add the rule, but don't enable it yet")]
+    pub disabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct LockRulesRemoveOptions {
+    #[clap(help = "Name of the rule to remove")]
+    pub id: String,
+
+    #[clap(short, long, help = "Bucket (defaults to `R2_BUCKET`)")]
+    pub bucket: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Parser)]
+pub struct UsageOptions {
+    #[clap(subcommand)]
+    pub cmd: UsageCommands,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct UsageRecordOptions {
+    #[clap(
+        long,
+        help = "History file to append to (default: ~/.config/r2-d2/usage.jsonl)"
+    )]
+    pub history: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Record every profile (or every account of the API token), see `overview --all-profiles`"
+    )]
+    pub all_profiles: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Parser)]
+pub struct UsageTrendOptions {
+    #[clap(
+        long,
+        help = "History file written by `usage record` (default: ~/.config/r2-d2/usage.jsonl)"
+    )]
+    pub history: Option<PathBuf>,
+
+    #[clap(short, long, help = "Only show buckets whose name contains this text")]
+    pub filter: Option<String>,
+
+    #[clap(
+        long,
+        default_value = "day",
+        value_parser = ["day", "week"],
+        help = "Show the growth per day or per week"
+    )]
+    pub per: String,
+
+    #[clap(
+        long,
+        default_value = "30d",
+        value_parser = parse_duration,
+        help = "Only use records of this period (e.g. `30d` or `12w`)"
+    )]
+    pub since: u64,
+}
+
 macro_rules! register_cli {
     ($name:ident { $($variant:ident($opts:ty)),* $(,)? }) => {
         #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Parser)]
@@ -103,14 +452,56 @@ macro_rules! register_cli {
     };
 }
 
+register_cli!(BucketCommands {
+    Create(BucketCreateOptions),
+    Info(BucketInfoOptions),
+});
+
+register_cli!(CorsCommands {
+    Get(CorsGetOptions),
+    Set(CorsSetOptions),
+    Delete(CorsDeleteOptions),
+});
+
+register_cli!(DomainCommands {
+    List(DomainListOptions),
+    Add(DomainAddOptions),
+    Remove(DomainRemoveOptions),
+    Managed(DomainManagedOptions),
+});
+
+register_cli!(LifecycleCommands {
+    Get(LifecycleGetOptions),
+    Set(LifecycleSetOptions),
+});
+
+register_cli!(LockRulesCommands {
+    List(LockRulesListOptions),
+    Add(LockRulesAddOptions),
+    Remove(LockRulesRemoveOptions),
+});
+
+register_cli!(UsageCommands {
+    Record(UsageRecordOptions),
+    Trend(UsageTrendOptions),
+});
+
 // Usage
 register_cli!(Commands {
     // main commands
 
     // helper commands
     Auth(AuthOptions),
+    Bucket(BucketOptions),
+    Cors(CorsOptions),
+    Doctor(DoctorOptions),
+    Domain(DomainOptions),
     Init(InitOptions),
+    Lifecycle(LifecycleOptions),
+    LockRules(LockRulesOptions),
     Overview(OverviewOptions),
+    TempCreds(TempCredsOptions),
     Upload(UploadOptions),
+    Usage(UsageOptions),
     Wipe(WipeOptions)
 });
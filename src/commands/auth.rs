@@ -1,17 +1,41 @@
 use crate::cli::{AuthOptions, Process};
-use crate::helpers::IntoPythonError;
+use crate::helpers::print_list_table;
+use crate::output::{OutputFormat, print_record};
 use crate::r2::R2D2;
+use crate::r2_auth_check::check_permissions;
 use anyhow::bail;
+use serde_json::json;
 
 impl Process for AuthOptions {
     async fn process(self) -> anyhow::Result<i32> {
-        let r2d2 = R2D2::guess().to_python_error("env")?;
+        let r2d2 = R2D2::guess().await?;
 
         if self.show {
             eprintln!("{:}", &r2d2);
         }
 
-        let verification = r2d2.verify_py().await?;
+        if self.check {
+            let checks = check_permissions(&r2d2, self.bucket).await;
+            let failed = checks.iter().filter(|check| check.failed()).count();
+
+            print_list_table(&checks)?;
+
+            if failed > 0 {
+                eprintln!("{failed} check(s) failed.");
+                return Ok(1);
+            }
+
+            let unknown = checks.iter().filter(|check| check.untested()).count();
+            if unknown > 0 {
+                eprintln!("All tested checks passed, {unknown} could not be tested.");
+                return Ok(0);
+            }
+
+            eprintln!("All checks passed.");
+            return Ok(0);
+        }
+
+        let verification = r2d2.verify().await?.into_error()?;
 
         // Obfuscate the verification ID
         let id = &verification.id;
@@ -21,6 +45,16 @@ impl Process for AuthOptions {
             id.clone()
         };
 
+        if !OutputFormat::is_table() {
+            print_record(&json!({
+                "id": obfuscated_id,
+                "status": verification.status,
+                "ok": verification.ok(),
+            }))?;
+
+            return Ok(i32::from(!verification.ok()));
+        }
+
         if verification.ok() {
             println!("Authorization ok: {obfuscated_id}");
             Ok(0)
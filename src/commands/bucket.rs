@@ -0,0 +1,392 @@
+#![allow(
+    unsafe_op_in_unsafe_fn,
+    reason = "pyo3 0.20's generated argument extraction predates edition 2024."
+)]
+
+use crate::cli::{BucketCreateOptions, BucketInfoOptions, BucketOptions, Process};
+use crate::commands::usage::R2Usage;
+use crate::helpers::{UnwrapIntoPythonError, future_pyresult_to_py, human_size, print_list_table};
+use crate::output::{OutputFormat, print_record};
+use crate::r2::{ApiResponse, BucketData, R2D2};
+use crate::r2_error::is_not_configured;
+use pyo3::{PyAny, PyResult, Python, prelude as pyo, pyclass, pymethods};
+use serde::Serialize;
+use tabled::Tabled;
+
+#[derive(Debug, Default, Clone)]
+pub struct CreateBucketOptions {
+    pub location_hint: Option<String>,
+    pub jurisdiction: Option<String>,
+    pub storage_class: Option<String>,
+}
+
+/// Body for `POST buckets` (the jurisdiction is sent as header instead)
+#[derive(Serialize, Debug)]
+pub struct CreateBucketRequest {
+    name: String,
+    #[serde(rename = "locationHint", skip_serializing_if = "Option::is_none")]
+    location_hint: Option<String>,
+    #[serde(rename = "storageClass", skip_serializing_if = "Option::is_none")]
+    storage_class: Option<String>,
+}
+
+impl CreateBucketOptions {
+    pub fn into_request(
+        self,
+        bucket: &str,
+    ) -> CreateBucketRequest {
+        CreateBucketRequest {
+            name: bucket.to_owned(),
+            location_hint: self.location_hint,
+            storage_class: self.storage_class,
+        }
+    }
+}
+
+#[pyclass(module = "r2_d2")]
+#[derive(Debug)]
+pub struct R2Bucket {
+    #[pyo3(get)]
+    pub name: String,
+    #[pyo3(get)]
+    pub creation_date: String,
+    #[pyo3(get)]
+    pub location: Option<String>,
+    #[pyo3(get)]
+    pub storage_class: Option<String>,
+}
+
+impl From<BucketData> for R2Bucket {
+    fn from(value: BucketData) -> Self {
+        Self {
+            name: value.name,
+            creation_date: value.creation_date,
+            location: value.location,
+            storage_class: value.storage_class,
+        }
+    }
+}
+
+#[pymethods]
+impl R2Bucket {
+    pub fn __str__(&self) -> String {
+        format!("<r2_d2.R2Bucket {}>", self.name)
+    }
+    pub fn __repr__(&self) -> String {
+        format!("<{self:#?}>")
+    }
+}
+
+pub async fn create_bucket_async(
+    name: String,
+    options: CreateBucketOptions,
+) -> PyResult<R2Bucket> {
+    let r2d2 = R2D2::guess().await.unwrap_or_raise()?;
+
+    let bucket = r2d2.create_bucket_py(&name, Some(options)).await?;
+
+    Ok(bucket.into())
+}
+
+#[pyo::pyfunction]
+#[pyo3(signature = (name, location_hint=None, jurisdiction=None, storage_class=None))]
+pub fn create_bucket(
+    py: Python<'_>,
+    name: String,
+    location_hint: Option<String>,
+    jurisdiction: Option<String>,
+    storage_class: Option<String>,
+) -> PyResult<&PyAny> {
+    let options = CreateBucketOptions {
+        location_hint,
+        jurisdiction,
+        storage_class,
+    };
+    let future = create_bucket_async(name, options);
+
+    future_pyresult_to_py(py, future)
+}
+
+impl Process for BucketOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        self.cmd.process().await
+    }
+}
+
+impl Process for BucketCreateOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let r2 = R2D2::guess().await?;
+
+        let options = CreateBucketOptions {
+            location_hint: self.location_hint,
+            jurisdiction: self.jurisdiction,
+            storage_class: self.storage_class,
+        };
+
+        let bucket = r2
+            .create_bucket(&self.name, Some(options))
+            .await?
+            .into_error()?;
+
+        eprintln!(
+            "Bucket `{}` created (location: {}, storage class: {}).",
+            bucket.name,
+            bucket.location.as_deref().unwrap_or("auto"),
+            bucket.storage_class.as_deref().unwrap_or("Standard"),
+        );
+
+        let public_url = if self.public {
+            let managed = r2
+                .set_managed_domain(Some(bucket.name.clone()), true)
+                .await?;
+            Some(format!("https://{}", managed.domain))
+        } else {
+            None
+        };
+
+        if !OutputFormat::is_table() {
+            let mut record = serde_json::to_value(&bucket)?;
+            record["public_url"] = public_url.into();
+            print_record(&record)?;
+        } else if let Some(public_url) = public_url {
+            println!("{public_url}");
+        }
+
+        Ok(0)
+    }
+}
+
+#[derive(Tabled, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct BucketInfoRow {
+    pub property: String,
+    pub value: String,
+    /// Structured `--format`s get the raw number instead of `value`
+    #[tabled(skip)]
+    #[serde(skip)]
+    pub number: Option<i64>,
+}
+
+impl BucketInfoRow {
+    fn new(
+        property: &str,
+        value: impl Into<String>,
+    ) -> Self {
+        Self {
+            property: property.to_owned(),
+            value: value.into(),
+            number: None,
+        }
+    }
+
+    fn count(
+        property: &str,
+        count: i64,
+    ) -> Self {
+        Self {
+            number: Some(count),
+            ..Self::new(property, count.to_string())
+        }
+    }
+
+    fn size(
+        property: &str,
+        bytes: i64,
+    ) -> Self {
+        Self {
+            number: Some(bytes),
+            ..Self::new(property, human_size(bytes))
+        }
+    }
+}
+
+/// Show the first line of the error, the report goes on with the other properties
+fn describe_error(error: &anyhow::Error) -> String {
+    // CORS and the like are 'not found' when they were never configured:
+    if is_not_configured(error) {
+        return String::from("none");
+    }
+
+    let message = error.to_string();
+    format!(
+        "unavailable: {}",
+        message.lines().next().unwrap_or_default()
+    )
+}
+
+fn or_unavailable<T>(
+    result: anyhow::Result<T>,
+    describe: impl FnOnce(T) -> String,
+) -> String {
+    result.map_or_else(|error| describe_error(&error), describe)
+}
+
+/// `none` or e.g. `2 (keep-30d, old-logs [disabled])`
+fn describe_rules(rules: &[(String, bool)]) -> String {
+    if rules.is_empty() {
+        return String::from("none");
+    }
+
+    let names: Vec<String> = rules
+        .iter()
+        .map(|(id, enabled)| {
+            if *enabled {
+                id.clone()
+            } else {
+                format!("{id} [disabled]")
+            }
+        })
+        .collect();
+
+    format!("{} ({})", rules.len(), names.join(", "))
+}
+
+/// The `config` object only exists in an initialized restic repository
+async fn has_restic_repo(
+    r2: &R2D2,
+    bucket: &str,
+) -> anyhow::Result<bool> {
+    let mut r2 = r2.clone();
+    r2.set_bucket(Some(bucket.to_owned()));
+
+    let op = r2.with_s3_credentials().await?.into_opendal_operator()?;
+
+    Ok(op.exists("config").await?)
+}
+
+fn usage_rows(usage: &R2Usage) -> Vec<BucketInfoRow> {
+    vec![
+        BucketInfoRow::count("objects", usage.object_count),
+        BucketInfoRow::size("size", usage.payload_size),
+        BucketInfoRow::size("metadata size", usage.metadata_size),
+        BucketInfoRow::count("pending uploads", usage.upload_count),
+        BucketInfoRow::count(
+            "infrequent access objects",
+            usage.infrequent_access_object_count,
+        ),
+        BucketInfoRow::size(
+            "infrequent access size",
+            usage.infrequent_access_payload_size,
+        ),
+        BucketInfoRow::size(
+            "infrequent access metadata size",
+            usage.infrequent_access_metadata_size,
+        ),
+        BucketInfoRow::count(
+            "infrequent access pending uploads",
+            usage.infrequent_access_upload_count,
+        ),
+    ]
+}
+
+pub async fn bucket_info(
+    r2: &R2D2,
+    bucket: &str,
+) -> anyhow::Result<Vec<BucketInfoRow>> {
+    let name = Some(bucket.to_owned());
+
+    // the bucket itself has to exist, everything else is reported as far as possible:
+    let data = r2.bucket(name.clone()).await?.into_error()?;
+
+    let (domains, usage, cors, lifecycle, lock_rules, restic) = futures::join!(
+        r2.bucket_domains(name.clone()),
+        r2.usage(name.clone()),
+        r2.cors(name.clone()),
+        r2.lifecycle(name.clone()),
+        r2.lock_rules(name.clone()),
+        has_restic_repo(r2, bucket),
+    );
+
+    let mut rows = vec![
+        BucketInfoRow::new("name", data.name),
+        BucketInfoRow::new("location", data.location.unwrap_or_default()),
+        BucketInfoRow::new(
+            "storage class",
+            data.storage_class
+                .unwrap_or_else(|| String::from("Standard")),
+        ),
+        BucketInfoRow::new("created", data.creation_date),
+        BucketInfoRow::new(
+            "domains",
+            or_unavailable(domains, |domains| {
+                if domains.is_empty() {
+                    String::from("none")
+                } else {
+                    domains.join(", ")
+                }
+            }),
+        ),
+    ];
+
+    match usage.and_then(ApiResponse::into_error) {
+        Ok(usage) => rows.extend(usage_rows(&usage.into())),
+        Err(error) => rows.push(BucketInfoRow::new("usage", describe_error(&error))),
+    }
+
+    rows.push(BucketInfoRow::new(
+        "CORS rules",
+        or_unavailable(cors, |policy| {
+            let rules: Vec<(String, bool)> = policy
+                .rules
+                .into_iter()
+                .enumerate()
+                .map(|(idx, rule)| (rule.id.unwrap_or_else(|| format!("rule {}", idx + 1)), true))
+                .collect();
+            describe_rules(&rules)
+        }),
+    ));
+    rows.push(BucketInfoRow::new(
+        "lifecycle rules",
+        or_unavailable(lifecycle, |policy| {
+            let rules: Vec<(String, bool)> = policy
+                .rules
+                .into_iter()
+                .map(|rule| (rule.id, rule.enabled))
+                .collect();
+            describe_rules(&rules)
+        }),
+    ));
+    rows.push(BucketInfoRow::new(
+        "lock rules",
+        or_unavailable(lock_rules, |list| {
+            let rules: Vec<(String, bool)> = list
+                .rules
+                .into_iter()
+                .map(|rule| (rule.id, rule.enabled))
+                .collect();
+            describe_rules(&rules)
+        }),
+    ));
+    rows.push(BucketInfoRow::new(
+        "restic repository",
+        or_unavailable(restic, |exists| {
+            String::from(if exists { "yes" } else { "no" })
+        }),
+    ));
+
+    Ok(rows)
+}
+
+impl Process for BucketInfoOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let r2 = R2D2::guess().await?;
+        let bucket = r2.bucket_or(&self.name)?;
+
+        let rows = bucket_info(&r2, &bucket).await?;
+
+        if OutputFormat::is_table() {
+            print_list_table(&rows)?;
+        } else {
+            // one record (property: value) instead of a list of pairs:
+            let report: serde_json::Map<String, serde_json::Value> = rows
+                .into_iter()
+                .map(|row| {
+                    let value = row.number.map_or_else(|| row.value.into(), Into::into);
+                    (row.property, value)
+                })
+                .collect();
+            print_record(&report)?;
+        }
+
+        Ok(0)
+    }
+}
@@ -0,0 +1,99 @@
+use crate::cli::{CorsDeleteOptions, CorsGetOptions, CorsOptions, CorsSetOptions, Process};
+use crate::helpers::print_diff;
+use crate::output::{OutputFormat, print_record};
+use crate::r2::{BucketCorsPolicy, R2D2};
+use crate::r2_error::is_not_configured;
+use anyhow::Context;
+use std::path::Path;
+
+/// Read a policy from a .toml file, or from json otherwise
+fn read_policy(path: &Path) -> anyhow::Result<BucketCorsPolicy> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("`{}` could not be read.", path.display()))?;
+
+    let is_toml = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"));
+
+    if is_toml {
+        toml::from_str(&contents).with_context(|| format!("Invalid TOML in `{}`", path.display()))
+    } else {
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid JSON in `{}`", path.display()))
+    }
+}
+
+impl Process for CorsOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        self.cmd.process().await
+    }
+}
+
+impl Process for CorsGetOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let r2 = R2D2::guess().await?;
+
+        // a bucket without CORS returns an error instead of an empty policy:
+        let policy = match r2.cors(self.bucket).await {
+            Ok(policy) => policy,
+            Err(error) if is_not_configured(&error) => BucketCorsPolicy::default(),
+            Err(error) => return Err(error),
+        };
+
+        if policy.rules.is_empty() && OutputFormat::is_table() {
+            eprintln!("No CORS policy configured.");
+            return Ok(0);
+        }
+
+        // the policy is JSON already, `table` can't show it any better:
+        print_record(&policy)?;
+
+        Ok(0)
+    }
+}
+
+impl Process for CorsSetOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let r2 = R2D2::guess().await?;
+        let bucket = r2.bucket_or(&self.bucket)?;
+
+        let policy = read_policy(&self.policy)?;
+        // a bucket without CORS returns an error instead of an empty policy:
+        let current = match r2.cors(Some(bucket.clone())).await {
+            Ok(current) => current,
+            Err(error) if is_not_configured(&error) => BucketCorsPolicy::default(),
+            Err(error) => return Err(error),
+        };
+
+        if current == policy {
+            eprintln!("CORS policy of `{bucket}` is already up to date.");
+            return Ok(0);
+        }
+
+        print_diff(
+            &serde_json::to_string_pretty(&current)?,
+            &serde_json::to_string_pretty(&policy)?,
+        );
+
+        if self.dry_run {
+            return Ok(0);
+        }
+
+        r2.set_cors(Some(bucket.clone()), &policy).await?;
+        eprintln!("CORS policy of `{bucket}` updated.");
+
+        Ok(0)
+    }
+}
+
+impl Process for CorsDeleteOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let r2 = R2D2::guess().await?;
+        let bucket = r2.bucket_or(&self.bucket)?;
+
+        r2.delete_cors(Some(bucket.clone())).await?;
+        eprintln!("CORS policy of `{bucket}` deleted.");
+
+        Ok(0)
+    }
+}
@@ -0,0 +1,32 @@
+use crate::cli::{DoctorOptions, Process};
+use crate::helpers::print_list_table;
+use crate::output::OutputFormat;
+use crate::r2::R2D2;
+use crate::r2_doctor::diagnose;
+
+impl Process for DoctorOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let mut r2 = R2D2::guess().await?;
+
+        if self.bucket.is_some() {
+            r2.set_bucket(self.bucket);
+        }
+
+        let findings = diagnose(&r2).await?;
+
+        if findings.is_empty() && OutputFormat::is_table() {
+            eprintln!("No problems found.");
+            return Ok(0);
+        }
+
+        print_list_table(&findings)?;
+
+        if findings.is_empty() {
+            return Ok(0);
+        }
+
+        eprintln!("Found {} unexpected object(s).", findings.len());
+
+        Ok(1)
+    }
+}
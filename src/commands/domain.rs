@@ -0,0 +1,123 @@
+use crate::cli::{
+    DomainAddOptions, DomainListOptions, DomainManagedOptions, DomainOptions, DomainRemoveOptions,
+    Process,
+};
+use crate::helpers::print_list_table;
+use crate::r2::{CustomBucketDomainData, ManagedBucketDomainData, R2D2};
+use serde::Serialize;
+use tabled::Tabled;
+
+#[derive(Tabled, Serialize)]
+pub struct DomainTable {
+    domain: String,
+    kind: String,
+    enabled: bool,
+    ownership: String,
+    ssl: String,
+}
+
+impl From<CustomBucketDomainData> for DomainTable {
+    fn from(value: CustomBucketDomainData) -> Self {
+        let (ownership, ssl) = value
+            .status
+            .map(|status| (status.ownership, status.ssl))
+            .unwrap_or_default();
+
+        Self {
+            domain: value.domain,
+            kind: String::from("custom"),
+            enabled: value.enabled,
+            ownership,
+            ssl,
+        }
+    }
+}
+
+impl From<ManagedBucketDomainData> for DomainTable {
+    fn from(value: ManagedBucketDomainData) -> Self {
+        Self {
+            domain: value.domain,
+            kind: String::from("r2.dev"),
+            enabled: value.enabled,
+            ownership: String::from("-"),
+            ssl: String::from("-"),
+        }
+    }
+}
+
+impl Process for DomainOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        self.cmd.process().await
+    }
+}
+
+impl Process for DomainListOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let r2 = R2D2::guess().await?;
+        let bucket = r2.bucket_or(&self.bucket)?;
+
+        let mut rows: Vec<DomainTable> = r2
+            .bucket_custom_domain_list(Some(bucket.clone()))
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        rows.push(r2.bucket_managed_domain_data(Some(bucket)).await?.into());
+
+        print_list_table(&rows)?;
+
+        Ok(0)
+    }
+}
+
+impl Process for DomainAddOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let r2 = R2D2::guess().await?;
+        let bucket = r2.bucket_or(&self.bucket)?;
+
+        let domain = r2
+            .attach_custom_domain(Some(bucket.clone()), &self.domain, &self.zone_id)
+            .await?;
+
+        eprintln!(
+            "Domain `{}` attached to `{bucket}`; check `r2-d2 domain list` until ownership and SSL are active.",
+            domain.domain
+        );
+
+        Ok(0)
+    }
+}
+
+impl Process for DomainRemoveOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let r2 = R2D2::guess().await?;
+        let bucket = r2.bucket_or(&self.bucket)?;
+
+        r2.detach_custom_domain(Some(bucket.clone()), &self.domain)
+            .await?;
+
+        eprintln!("Domain `{}` detached from `{bucket}`.", self.domain);
+
+        Ok(0)
+    }
+}
+
+impl Process for DomainManagedOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let r2 = R2D2::guess().await?;
+        let bucket = r2.bucket_or(&self.bucket)?;
+        let enable = self.action == "enable";
+
+        let managed = r2.set_managed_domain(Some(bucket.clone()), enable).await?;
+
+        if managed.enabled {
+            eprintln!("Public access via r2.dev enabled for `{bucket}`:");
+            println!("https://{}", managed.domain);
+        } else {
+            eprintln!("Public access via r2.dev disabled for `{bucket}`.");
+        }
+
+        Ok(0)
+    }
+}
@@ -1,8 +1,13 @@
 #![expect(dead_code, reason = "This file is a work in progress")]
 
 use crate::cli::{InitOptions, Process};
+use crate::helpers::print_list_table;
 use crate::r2::{R2D2, ResticRepository};
+use crate::r2_error::R2Error;
+use rustic_core::repofile::SnapshotFile;
 use rustic_core::{BackupOptions, ConfigOptions, KeyOptions, PathList, SnapshotOptions};
+use serde::Serialize;
+use tabled::Tabled;
 
 pub fn init_repo(repo: ResticRepository) -> anyhow::Result<()> {
     let key_opts = KeyOptions::default();
@@ -36,21 +41,51 @@ fn create_snapshot(repo: ResticRepository) -> anyhow::Result<()> {
 }
 
 fn get_snapshots(repo: ResticRepository) -> anyhow::Result<()> {
-    let repo = repo.open()?;
+    let repo = repo.open().map_err(|e| {
+        anyhow::Error::new(e).context(R2Error::repository("Could not open the restic repository"))
+    })?;
 
     // Get all snapshots from the repository
-    let snaps = repo.get_all_snapshots()?;
+    let mut snaps = repo.get_all_snapshots()?;
+    snaps.sort_unstable_by_key(|snap| snap.time);
 
-    dbg!(snaps.len());
+    let rows: Vec<SnapshotRow> = snaps.iter().map(SnapshotRow::from).collect();
+    print_list_table(&rows)?;
 
     Ok(())
 }
 
+#[derive(Tabled, Serialize)]
+pub struct SnapshotRow {
+    id: String,
+    time: String,
+    hostname: String,
+    paths: String,
+    tags: String,
+}
+
+impl From<&SnapshotFile> for SnapshotRow {
+    fn from(snap: &SnapshotFile) -> Self {
+        Self {
+            id: snap.id.to_hex().to_string(),
+            time: snap.time.to_rfc3339(),
+            hostname: snap.hostname.clone(),
+            paths: snap.paths.to_string(),
+            tags: snap.tags.to_string(),
+        }
+    }
+}
+
 impl Process for InitOptions {
     async fn process(self) -> anyhow::Result<i32> {
-        let r2 = R2D2::guess()?;
-
-        let repo = r2.into_rustic()?;
+        let r2 = R2D2::guess().await?;
+
+        let repo = r2
+            .with_s3_credentials()
+            .await?
+            .with_lock_rules()
+            .await?
+            .into_rustic()?;
 
         // Init repository
         // init_repo(repo)?;
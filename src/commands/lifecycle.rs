@@ -0,0 +1,172 @@
+use crate::cli::{LifecycleGetOptions, LifecycleOptions, LifecycleSetOptions, Process};
+use crate::helpers::{print_diff, print_list_table};
+use crate::output::OutputFormat;
+use crate::r2::{
+    BucketLifecyclePolicy, LifecycleCondition, LifecycleRule, LifecycleRuleConditions,
+    LifecycleTransition, R2D2, StorageClassTransition,
+};
+use serde::Serialize;
+use tabled::Tabled;
+
+#[derive(Tabled, Serialize)]
+pub struct LifecycleRuleTable {
+    id: String,
+    enabled: bool,
+    prefix: String,
+    actions: String,
+}
+
+impl From<LifecycleRule> for LifecycleRuleTable {
+    fn from(rule: LifecycleRule) -> Self {
+        let mut actions = vec![];
+
+        if let Some(transition) = rule.abort_multipart_uploads_transition {
+            actions.push(format!("abort multipart {}", transition.condition));
+        }
+
+        for transition in rule.storage_class_transitions {
+            actions.push(format!(
+                "to {} {}",
+                transition.storage_class, transition.condition
+            ));
+        }
+
+        if let Some(transition) = rule.delete_objects_transition {
+            actions.push(format!("delete {}", transition.condition));
+        }
+
+        Self {
+            id: rule.id,
+            enabled: rule.enabled,
+            prefix: if rule.conditions.prefix.is_empty() {
+                String::from("*")
+            } else {
+                rule.conditions.prefix
+            },
+            actions: actions.join("\n"),
+        }
+    }
+}
+
+const fn after(max_age: u64) -> LifecycleTransition {
+    LifecycleTransition {
+        condition: LifecycleCondition::Age { max_age },
+    }
+}
+
+impl LifecycleSetOptions {
+    fn to_rule(&self) -> LifecycleRule {
+        let prefix = self.prefix.clone().unwrap_or_default();
+        let id = self.id.clone().unwrap_or_else(|| {
+            if prefix.is_empty() {
+                String::from("r2-d2")
+            } else {
+                format!("r2-d2-{}", prefix.trim_end_matches('/').replace('/', "-"))
+            }
+        });
+
+        LifecycleRule {
+            id,
+            enabled: !self.disabled,
+            conditions: LifecycleRuleConditions { prefix },
+            delete_objects_transition: self.expire_after.map(after),
+            abort_multipart_uploads_transition: self.abort_multipart_after.map(after),
+            storage_class_transitions: self
+                .transition_ia_after
+                .map(|max_age| StorageClassTransition {
+                    condition: LifecycleCondition::Age { max_age },
+                    storage_class: String::from("InfrequentAccess"),
+                })
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Only the actions given on the command line replace those of an existing rule
+    fn merge_into(
+        &self,
+        existing: &mut LifecycleRule,
+    ) {
+        let rule = self.to_rule();
+
+        existing.enabled = rule.enabled;
+
+        if self.prefix.is_some() {
+            existing.conditions = rule.conditions;
+        }
+
+        if rule.delete_objects_transition.is_some() {
+            existing.delete_objects_transition = rule.delete_objects_transition;
+        }
+
+        if rule.abort_multipart_uploads_transition.is_some() {
+            existing.abort_multipart_uploads_transition = rule.abort_multipart_uploads_transition;
+        }
+
+        for transition in rule.storage_class_transitions {
+            existing
+                .storage_class_transitions
+                .retain(|it| it.storage_class != transition.storage_class);
+            existing.storage_class_transitions.push(transition);
+        }
+    }
+}
+
+impl Process for LifecycleOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        self.cmd.process().await
+    }
+}
+
+impl Process for LifecycleGetOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let r2 = R2D2::guess().await?;
+
+        let policy = r2.lifecycle(self.bucket).await?;
+        let rows: Vec<LifecycleRuleTable> = policy.rules.into_iter().map(Into::into).collect();
+
+        if rows.is_empty() && OutputFormat::is_table() {
+            eprintln!("No lifecycle rules configured.");
+        } else {
+            print_list_table(&rows)?;
+        }
+
+        Ok(0)
+    }
+}
+
+impl Process for LifecycleSetOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let r2 = R2D2::guess().await?;
+        let bucket = r2.bucket_or(&self.bucket)?;
+
+        let current: BucketLifecyclePolicy = r2.lifecycle(Some(bucket.clone())).await?;
+        let rule = self.to_rule();
+
+        // update the rule with the same id, or add it:
+        let mut policy = current.clone();
+        match policy.rules.iter_mut().find(|it| it.id == rule.id) {
+            Some(existing) => self.merge_into(existing),
+            None => policy.rules.push(rule.clone()),
+        }
+
+        if policy == current {
+            eprintln!("Lifecycle rules of `{bucket}` are already up to date.");
+            return Ok(0);
+        }
+
+        print_diff(
+            &serde_json::to_string_pretty(&current)?,
+            &serde_json::to_string_pretty(&policy)?,
+        );
+
+        if self.dry_run {
+            return Ok(0);
+        }
+
+        r2.set_lifecycle(Some(bucket.clone()), &policy).await?;
+        eprintln!("Lifecycle rule `{}` set on `{bucket}`.", rule.id);
+
+        Ok(0)
+    }
+}
@@ -1,12 +1,13 @@
 use crate::r2::{Direction, QueryString, to_query_part};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct ListOptions {
-    cursor: Option<String>,
-    direction: Option<Direction>,
-    order: Option<String>,
-    per_page: Option<u32>,
-    start_after: Option<String>,
+    pub cursor: Option<String>,
+    pub direction: Option<Direction>,
+    pub name_contains: Option<String>,
+    pub order: Option<String>,
+    pub per_page: Option<u32>,
+    pub start_after: Option<String>,
 }
 
 impl QueryString for ListOptions {
@@ -21,6 +22,10 @@ impl QueryString for ListOptions {
             parts.push(to_query_part("direction", direction.to_string()));
         }
 
+        if let Some(name_contains) = &self.name_contains {
+            parts.push(to_query_part("name_contains", name_contains));
+        }
+
         if let Some(order) = &self.order {
             parts.push(to_query_part("order", order));
         }
@@ -0,0 +1,105 @@
+use crate::cli::{
+    LockRulesAddOptions, LockRulesListOptions, LockRulesOptions, LockRulesRemoveOptions, Process,
+};
+use crate::helpers::print_list_table;
+use crate::output::OutputFormat;
+use crate::r2::{BucketLockCondition, BucketLockRule, R2D2};
+use anyhow::bail;
+use serde::Serialize;
+use tabled::Tabled;
+
+#[derive(Tabled, Serialize)]
+pub struct LockRuleTable {
+    id: String,
+    enabled: bool,
+    prefix: String,
+    condition: String,
+}
+
+impl From<BucketLockRule> for LockRuleTable {
+    fn from(rule: BucketLockRule) -> Self {
+        Self {
+            id: rule.id,
+            enabled: rule.enabled,
+            prefix: rule.prefix.unwrap_or_else(|| String::from("*")),
+            condition: rule.condition.to_string(),
+        }
+    }
+}
+
+impl Process for LockRulesOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        self.cmd.process().await
+    }
+}
+
+impl Process for LockRulesListOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let r2 = R2D2::guess().await?;
+
+        let rules = r2.lock_rules(self.bucket).await?;
+        let rows: Vec<LockRuleTable> = rules.rules.into_iter().map(Into::into).collect();
+
+        if rows.is_empty() && OutputFormat::is_table() {
+            eprintln!("No lock rules configured.");
+        } else {
+            print_list_table(&rows)?;
+        }
+
+        Ok(0)
+    }
+}
+
+impl Process for LockRulesAddOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let r2 = R2D2::guess().await?;
+        let bucket = r2.bucket_or(&self.bucket)?;
+
+        let mut rules = r2.lock_rules(Some(bucket.clone())).await?;
+
+        if rules.rules.iter().any(|rule| rule.id == self.id) {
+            bail!(
+                "Lock rule `{}` already exists on bucket `{bucket}`.",
+                self.id
+            );
+        }
+
+        let condition = match (self.max_age, self.date) {
+            (Some(max_age_seconds), _) => BucketLockCondition::Age { max_age_seconds },
+            (None, Some(date)) => BucketLockCondition::Date { date },
+            (None, None) => BucketLockCondition::Indefinite,
+        };
+
+        rules.rules.push(BucketLockRule {
+            id: self.id.clone(),
+            enabled: !self.disabled,
+            prefix: self.prefix,
+            condition,
+        });
+
+        r2.set_lock_rules(Some(bucket.clone()), &rules).await?;
+
+        eprintln!("Lock rule `{}` added to bucket `{bucket}`.", self.id);
+        Ok(0)
+    }
+}
+
+impl Process for LockRulesRemoveOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let r2 = R2D2::guess().await?;
+        let bucket = r2.bucket_or(&self.bucket)?;
+
+        let mut rules = r2.lock_rules(Some(bucket.clone())).await?;
+        let before = rules.rules.len();
+        rules.rules.retain(|rule| rule.id != self.id);
+
+        if rules.rules.len() == before {
+            bail!("Lock rule `{}` not found on bucket `{bucket}`.", self.id);
+        }
+
+        r2.set_lock_rules(Some(bucket.clone()), &rules).await?;
+
+        eprintln!("Lock rule `{}` removed from bucket `{bucket}`.", self.id);
+        Ok(0)
+    }
+}
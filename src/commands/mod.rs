@@ -1,7 +1,15 @@
 pub mod auth;
+pub mod bucket;
+pub mod cors;
+pub mod doctor;
+pub mod domain;
 pub mod init;
+pub mod lifecycle;
 pub mod list;
+pub mod lock_rules;
 pub mod overview;
+pub mod temp_creds;
 pub mod upload;
 pub mod usage;
+pub mod usage_history;
 pub mod wipe;
@@ -1,13 +1,20 @@
 use crate::cli::{OverviewOptions, Process};
-use crate::commands::usage::gather_usage_info;
+use crate::commands::usage::{gather_all_profiles_usage, gather_usage_info};
 use crate::helpers::print_table;
 use crate::r2::R2D2;
 
 impl Process for OverviewOptions {
     async fn process(self) -> anyhow::Result<i32> {
-        let r2 = R2D2::guess()?;
-        let rows = gather_usage_info(&r2).await?;
-        print_table(&rows);
+        if self.all_profiles {
+            let rows = gather_all_profiles_usage(&self).await?;
+            print_table(&rows)?;
+
+            return Ok(0);
+        }
+
+        let r2 = R2D2::guess().await?;
+        let rows = gather_usage_info(&r2, &self).await?;
+        print_table(&rows)?;
 
         Ok(0)
     }
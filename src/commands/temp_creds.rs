@@ -0,0 +1,52 @@
+use crate::cli::{Process, TempCredsOptions};
+use crate::output::{OutputFormat, print_record};
+use crate::r2::{R2D2, TempCredentialsRequest};
+use serde_json::json;
+
+impl Process for TempCredsOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        // the parent key may be derived from the API token:
+        let r2 = R2D2::guess().await?.with_s3_credentials().await?;
+        let bucket = r2.bucket_or(&self.bucket)?;
+
+        let request = TempCredentialsRequest {
+            bucket: bucket.clone(),
+            parent_access_key_id: r2.access_key_id()?,
+            permission: self.permission,
+            ttl_seconds: self.ttl,
+            prefixes: self.prefix,
+            objects: vec![],
+        };
+
+        let credentials = r2.temp_credentials(&request).await?;
+
+        if !OutputFormat::is_table() {
+            print_record(&json!({
+                "account_id": r2.account_id(),
+                "jurisdiction": r2.jurisdiction(),
+                "endpoint": r2.endpoint(),
+                "bucket": bucket,
+                "access_key_id": credentials.access_key_id,
+                "secret_access_key": credentials.secret_access_key,
+                "session_token": credentials.session_token,
+            }))?;
+
+            return Ok(0);
+        }
+
+        // env-file format, so the output can be used as `.r2` on the backup host:
+        println!("R2_ACCOUNT_ID={}", r2.account_id());
+        if let Some(jurisdiction) = r2.jurisdiction() {
+            println!("R2_JURISDICTION={jurisdiction}");
+        }
+        if let Some(endpoint) = r2.endpoint() {
+            println!("R2_ENDPOINT={endpoint}");
+        }
+        println!("R2_BUCKET={bucket}");
+        println!("R2_ACCESS_KEY_ID={}", credentials.access_key_id);
+        println!("R2_SECRET_ACCESS_KEY={}", credentials.secret_access_key);
+        println!("R2_SESSION_TOKEN={}", credentials.session_token);
+
+        Ok(0)
+    }
+}
@@ -1,17 +1,29 @@
 use crate::cli::{Process, UploadOptions};
-use crate::helpers::{IntoPythonError, UnwrapIntoPythonError};
+use crate::output::{OutputFormat, print_record};
 use crate::r2::R2D2;
 use crate::r2_upload::upload_file;
+use serde_json::json;
 
 impl Process for UploadOptions {
     async fn process(self) -> anyhow::Result<i32> {
         // subcommand 'upload':
-        let r2 = R2D2::guess().to_python_error("env")?;
-        // upload_file(&r2, "/home/robin/Downloads/sport.vst".to_string(), None).await.unwrap_or_raise()?;
-        // upload_file(&r2, "/home/robin/Downloads/kopstootje.jpg".to_string(), None).await?;
-        upload_file(&r2, "/home/robin/Downloads/dwhn.mp4".to_string(), None)
-            .await
-            .unwrap_or_raise()?;
+        let r2 = R2D2::guess().await?;
+        let url = upload_file(&r2, self.file, self.bucket).await?;
+
+        if !OutputFormat::is_table() {
+            // an empty url means there is no public domain (or no API token to find it):
+            print_record(&json!({ "url": url }))?;
+        } else if url.is_empty() && !r2.has_api_key() {
+            eprintln!(
+                "Uploaded, but the public url needs `R2_API_KEY` to look up the bucket domain."
+            );
+        } else if url.is_empty() {
+            eprintln!(
+                "Uploaded, but the bucket has no public domain (see `r2-d2 domain managed enable`)."
+            );
+        } else {
+            println!("{url}");
+        }
 
         Ok(0)
     }
@@ -1,13 +1,17 @@
 use crate::commands::list::ListOptions;
-use byte_unit::{Byte, UnitType};
-use futures::future;
+use futures::{StreamExt, future, stream};
 use owo_colors::OwoColorize;
 use pyo3::{PyAny, PyResult, Python, prelude as pyo, pyclass, pymethods};
-use std::collections::BTreeMap;
+use serde::Serialize;
 use tabled::Tabled;
 
-use crate::helpers::{UnwrapIntoPythonError, future_pyresult_to_py, sotoi};
-use crate::r2::{R2D2, UsageResultData};
+use crate::cli::OverviewOptions;
+use crate::helpers::{UnwrapIntoPythonError, future_pyresult_to_py, human_size, sotoi};
+use crate::output::OutputFormat;
+use crate::r2::{ApiResponse, R2D2, UsageResultData};
+
+/// Usage requests in flight at once, large accounts get rate limited otherwise
+pub const USAGE_CONCURRENCY: usize = 8;
 
 #[pyclass(module = "r2_d2")]
 #[derive(Debug)]
@@ -60,7 +64,7 @@ impl R2Usage {
 
 #[allow(clippy::unused_async)]
 pub async fn usage_async() -> PyResult<R2Usage> {
-    let r2d2 = R2D2::guess().unwrap_or_raise()?;
+    let r2d2 = R2D2::guess().await.unwrap_or_raise()?;
 
     let usage = r2d2.usage_py(None).await?;
     let usage_py: R2Usage = usage.into();
@@ -75,25 +79,102 @@ pub fn usage(py: Python<'_>) -> PyResult<&PyAny> {
     future_pyresult_to_py(py, future)
 }
 
-#[derive(Tabled)]
+/// Structured `--format`s get the raw numbers (`usage`) instead of the display strings
+#[derive(Tabled, Serialize)]
 pub struct UsageTable {
     bucket_name: String,
-    raw_size: i64,
+    status: String,
+    #[serde(skip)]
+    objects: String,
+    #[serde(skip)]
+    metadata_size: String,
+    #[serde(skip)]
+    infrequent_access_size: String,
+    #[serde(skip)]
+    pending_uploads: String,
+    #[serde(skip)]
+    raw_size: String,
+    #[serde(skip)]
     human_size: String,
+    #[tabled(skip)]
+    #[serde(flatten)]
+    usage: Option<UsageTotals>,
+}
+
+/// The numbers behind a row (standard storage, except `infrequent_access_size`), summed up for the footer
+#[derive(Serialize, Debug, Clone, Copy, Default)]
+struct UsageTotals {
+    objects: i64,
+    size: i64,
+    metadata_size: i64,
+    infrequent_access_size: i64,
+    pending_uploads: i64,
+}
+
+impl From<&R2Usage> for UsageTotals {
+    fn from(usage: &R2Usage) -> Self {
+        Self {
+            objects: usage.object_count,
+            size: usage.payload_size,
+            metadata_size: usage.metadata_size,
+            infrequent_access_size: usage.infrequent_access_payload_size,
+            pending_uploads: usage.upload_count,
+        }
+    }
+}
+
+impl UsageTotals {
+    const fn add(
+        self,
+        other: Self,
+    ) -> Self {
+        Self {
+            objects: self.objects + other.objects,
+            size: self.size + other.size,
+            metadata_size: self.metadata_size + other.metadata_size,
+            infrequent_access_size: self.infrequent_access_size + other.infrequent_access_size,
+            pending_uploads: self.pending_uploads + other.pending_uploads,
+        }
+    }
 }
 
 impl UsageTable {
-    pub fn new<S: Into<String>>(
+    fn new<S: Into<String>>(
+        name: S,
+        usage: UsageTotals,
+        status: String,
+    ) -> Self {
+        Self {
+            bucket_name: name.into(),
+            objects: usage.objects.to_string(),
+            raw_size: usage.size.to_string(),
+            human_size: human_size(usage.size),
+            metadata_size: human_size(usage.metadata_size),
+            infrequent_access_size: human_size(usage.infrequent_access_size),
+            pending_uploads: usage.pending_uploads.to_string(),
+            status,
+            usage: Some(usage),
+        }
+    }
+
+    /// A bucket whose usage couldn't be fetched, so it can't silently go missing
+    fn error<S: Into<String>>(
         name: S,
-        size: i64,
+        error: &anyhow::Error,
     ) -> Self {
-        let byte = Byte::from_i64(size)
-            .unwrap_or_default()
-            .get_appropriate_unit(UnitType::Decimal);
+        let message = error.to_string();
+        let unknown = || String::from("?");
+
         Self {
             bucket_name: name.into(),
-            raw_size: size,
-            human_size: format!("{byte:#.2}"),
+            objects: unknown(),
+            raw_size: unknown(),
+            human_size: unknown(),
+            metadata_size: unknown(),
+            infrequent_access_size: unknown(),
+            pending_uploads: unknown(),
+            status: format!("error: {}", message.lines().next().unwrap_or_default()),
+            usage: None,
         }
     }
 
@@ -105,40 +186,184 @@ impl UsageTable {
         self
     }
 }
-fn calculate_sum(rows: &[UsageTable]) -> i64 {
-    rows.iter().fold(0, |sum, row| sum + row.raw_size)
+
+fn calculate_sum(rows: &[UsageTable]) -> UsageTotals {
+    rows.iter()
+        .filter_map(|row| row.usage)
+        .fold(UsageTotals::default(), UsageTotals::add)
 }
 
-pub async fn gather_usage_info(r2: &R2D2) -> anyhow::Result<Vec<UsageTable>> {
-    // todo: move elsewhere
-    // 1. list buckets
-    // 2. gather usage data
-    // 3. return table (str)
-    let buckets = r2.list_py(Some(ListOptions::default())).await?;
+/// `name` keeps the API order, the others put the largest buckets (and then failed ones) first
+fn sort_rows(
+    rows: &mut [UsageTable],
+    sort: &str,
+) {
+    let key = |row: &UsageTable| {
+        row.usage.map(|usage| match sort {
+            "size" => usage.size,
+            "objects" => usage.objects,
+            "uploads" => usage.pending_uploads,
+            _ => 0,
+        })
+    };
 
-    let bucket_names: Vec<String> = buckets.into_iter().map(|bucket| bucket.name).collect();
+    if sort != "name" {
+        rows.sort_by_key(|row| std::cmp::Reverse(key(row)));
+    }
+}
 
-    let promises: Vec<_> = bucket_names
+/// Sum of the rows, with the number of rows that failed as status
+fn footer(
+    label: &str,
+    usage: UsageTotals,
+    failed: &[(usize, &str)],
+) -> UsageTable {
+    let failed: Vec<String> = failed
         .iter()
-        .map(|bucket_name| r2.usage_py(Some(bucket_name.clone())))
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, what)| format!("{count} {what}(s)"))
         .collect();
 
-    let results = future::join_all(promises).await;
+    let status = if failed.is_empty() {
+        String::from("ok")
+    } else {
+        format!("incomplete: {} failed", failed.join(", "))
+    };
 
-    let map: BTreeMap<_, R2Usage> = bucket_names
+    UsageTable::new(label, usage, status).bold()
+}
+
+fn count_failed(rows: &[UsageTable]) -> usize {
+    rows.iter().filter(|row| row.usage.is_none()).count()
+}
+
+/// One row per bucket of this account, without footer
+async fn bucket_rows(
+    r2: &R2D2,
+    options: &OverviewOptions,
+) -> anyhow::Result<Vec<UsageTable>> {
+    let list_options = ListOptions {
+        name_contains: options.filter.clone(),
+        ..Default::default()
+    };
+    let buckets = r2.list(Some(list_options)).await?.into_error()?.buckets;
+
+    let mut bucket_names: Vec<String> = buckets.into_iter().map(|bucket| bucket.name).collect();
+    bucket_names.sort();
+
+    // `buffered` keeps the order of the buckets, so the results can be zipped below:
+    let results: Vec<_> = stream::iter(bucket_names.clone())
+        .map(|bucket_name| r2.usage(Some(bucket_name)))
+        .buffered(USAGE_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut rows: Vec<UsageTable> = bucket_names
         .into_iter()
         .zip(results)
-        // only keep Ok values, turn from UsageResultData into R2Usage
-        .filter_map(|(key, value)| value.map_or(None, |value| Some((key, value.into()))))
+        .map(
+            |(name, result)| match result.and_then(ApiResponse::into_error) {
+                Ok(usage) => UsageTable::new(
+                    name,
+                    UsageTotals::from(&R2Usage::from(usage)),
+                    String::from("ok"),
+                ),
+                Err(error) => UsageTable::error(name, &error),
+            },
+        )
         .collect();
 
-    let mut rows: Vec<UsageTable> = map
-        .iter()
-        .map(|(k, v)| UsageTable::new(k.clone(), v.payload_size))
-        .collect();
+    sort_rows(&mut rows, &options.sort);
 
-    // footer:
-    rows.push(UsageTable::new("total", calculate_sum(&rows)).bold());
+    Ok(rows)
+}
+
+pub async fn gather_usage_info(
+    r2: &R2D2,
+    options: &OverviewOptions,
+) -> anyhow::Result<Vec<UsageTable>> {
+    // 1. list buckets
+    // 2. gather usage data
+    // 3. return table rows
+    let mut rows = bucket_rows(r2, options).await?;
+
+    // in `--format json|csv|toml`, a footer would look like just another bucket:
+    if OutputFormat::is_table() {
+        let failed = count_failed(&rows);
+        rows.push(footer("total", calculate_sum(&rows), &[(failed, "bucket")]));
+    }
+
+    Ok(rows)
+}
+
+/// `overview --all-profiles`: `UsageTable` with the account it belongs to
+#[derive(Tabled, Serialize)]
+pub struct AccountUsageTable {
+    account: String,
+    #[tabled(inline)]
+    #[serde(flatten)]
+    row: UsageTable,
+}
+
+/// Usage of every profile/account at once, with a subtotal per account and a grand total
+/// (only in the table, like `gather_usage_info`)
+pub async fn gather_all_profiles_usage(
+    options: &OverviewOptions
+) -> anyhow::Result<Vec<AccountUsageTable>> {
+    let (accounts, clients): (Vec<_>, Vec<_>) = R2D2::all_profiles().await?.into_iter().unzip();
+
+    let results = future::join_all(
+        clients
+            .into_iter()
+            .map(|client| async move { bucket_rows(&client?, options).await }),
+    )
+    .await;
+
+    let mut rows = Vec::new();
+    let mut total = UsageTotals::default();
+    let (mut failed_accounts, mut failed_buckets) = (0, 0);
+
+    for (account, result) in accounts.into_iter().zip(results) {
+        let with_account = |row| AccountUsageTable {
+            account: account.clone(),
+            row,
+        };
+
+        match result {
+            Ok(buckets) => {
+                let subtotal = calculate_sum(&buckets);
+                let failed = count_failed(&buckets);
+
+                total = total.add(subtotal);
+                failed_buckets += failed;
+
+                rows.extend(buckets.into_iter().map(with_account));
+                if OutputFormat::is_table() {
+                    rows.push(with_account(footer(
+                        "subtotal",
+                        subtotal,
+                        &[(failed, "bucket")],
+                    )));
+                }
+            },
+            Err(error) => {
+                // the whole account is missing from the totals:
+                failed_accounts += 1;
+                rows.push(with_account(UsageTable::error("*", &error)));
+            },
+        }
+    }
+
+    if OutputFormat::is_table() {
+        rows.push(AccountUsageTable {
+            account: String::from("all"),
+            row: footer(
+                "total",
+                total,
+                &[(failed_accounts, "account"), (failed_buckets, "bucket")],
+            ),
+        });
+    }
 
     Ok(rows)
 }
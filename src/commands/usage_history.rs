@@ -0,0 +1,458 @@
+use crate::cli::{Process, UsageOptions, UsageRecordOptions, UsageTrendOptions};
+use crate::commands::list::ListOptions;
+use crate::commands::usage::USAGE_CONCURRENCY;
+use crate::helpers::{human_size, print_table, sotoi};
+use crate::output::OutputFormat;
+use crate::r2::{ApiResponse, R2D2, UsageResultData};
+use anyhow::Context;
+use chrono::{DateTime, Datelike, NaiveDate, SecondsFormat, TimeDelta, Utc};
+use futures::{StreamExt, stream};
+use owo_colors::OwoColorize;
+use resolve_path::PathResolveExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tabled::Tabled;
+
+/// Default for `--history`
+const HISTORY_FILE: &str = "~/.config/r2-d2/usage.jsonl";
+
+const SECONDS_PER_DAY: f64 = 60.0 * 60.0 * 24.0;
+
+/// Time of measurement and stored bytes of one bucket
+type Sample = (DateTime<Utc>, i64);
+
+/// One line of the history file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UsageRecord {
+    /// RFC 3339, when `usage record` ran
+    pub recorded_at: String,
+    pub account_id: String,
+    pub bucket: String,
+    pub usage: UsageResultData,
+}
+
+impl UsageRecord {
+    /// The API's `end` of the measurement if it can be parsed, the time of recording otherwise
+    fn measured_at(&self) -> Option<DateTime<Utc>> {
+        self.usage
+            .end
+            .as_deref()
+            .into_iter()
+            .chain([self.recorded_at.as_str()])
+            .find_map(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.with_timezone(&Utc))
+    }
+
+    /// Stored bytes, standard and infrequent access
+    fn size(&self) -> i64 {
+        sotoi(self.usage.payload_size.clone())
+            + sotoi(self.usage.infrequent_access_payload_size.clone())
+    }
+}
+
+fn history_path(history: Option<PathBuf>) -> PathBuf {
+    let path = history.unwrap_or_else(|| PathBuf::from(HISTORY_FILE));
+
+    path.try_resolve()
+        .map_or_else(|_| path.clone(), std::borrow::Cow::into_owned)
+}
+
+/// Current usage of every bucket of this account; buckets that fail are reported and counted
+async fn bucket_records(
+    r2: &R2D2,
+    recorded_at: &str,
+) -> anyhow::Result<(Vec<UsageRecord>, usize)> {
+    let buckets = r2
+        .list(Some(ListOptions::default()))
+        .await?
+        .into_error()?
+        .buckets;
+    let bucket_names: Vec<String> = buckets.into_iter().map(|bucket| bucket.name).collect();
+
+    let results: Vec<_> = stream::iter(bucket_names.clone())
+        .map(|bucket_name| r2.usage(Some(bucket_name)))
+        .buffered(USAGE_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut records = Vec::new();
+    let mut failed = 0;
+
+    for (bucket, result) in bucket_names.into_iter().zip(results) {
+        match result.and_then(ApiResponse::into_error) {
+            Ok(usage) => records.push(UsageRecord {
+                recorded_at: recorded_at.to_owned(),
+                account_id: r2.account_id().to_owned(),
+                bucket,
+                usage,
+            }),
+            Err(error) => {
+                failed += 1;
+                eprintln!(
+                    "{} usage of `{bucket}` could not be fetched: {error}",
+                    "Warning:".yellow()
+                );
+            },
+        }
+    }
+
+    Ok((records, failed))
+}
+
+fn append_records(
+    path: &Path,
+    records: &[UsageRecord],
+) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Could not create {}", parent.display()))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Could not open {}", path.display()))?;
+
+    // write all lines at once, so an interrupted run doesn't leave half a record:
+    let mut lines = String::new();
+    for record in records {
+        lines.push_str(&serde_json::to_string(record)?);
+        lines.push('\n');
+    }
+
+    file.write_all(lines.as_bytes())
+        .with_context(|| format!("Could not write to {}", path.display()))
+}
+
+fn read_records(path: &Path) -> anyhow::Result<Vec<UsageRecord>> {
+    let contents = fs::read_to_string(path).with_context(|| {
+        format!(
+            "Could not read {} (create it with `r2-d2 usage record`)",
+            path.display()
+        )
+    })?;
+
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            serde_json::from_str(line).with_context(|| {
+                format!("Invalid record on line {} of {}", idx + 1, path.display())
+            })
+        })
+        .collect()
+}
+
+/// Least-squares growth in bytes per second, `None` without at least two points in time
+#[expect(
+    clippy::cast_precision_loss,
+    reason = "Sizes and timespans stay far below 2^52."
+)]
+fn growth_rate(samples: &[Sample]) -> Option<f64> {
+    let start = samples.first()?.0;
+    let points: Vec<(f64, f64)> = samples
+        .iter()
+        .map(|(time, size)| ((*time - start).num_seconds() as f64, *size as f64))
+        .collect();
+
+    let count = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / count;
+
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+
+    (variance > 0.0).then(|| covariance / variance)
+}
+
+/// Midnight (UTC) at the start of next month
+fn month_end(now: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = if now.month() == 12 {
+        (now.year() + 1, 1)
+    } else {
+        (now.year(), now.month() + 1)
+    };
+
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map_or(now, |date| date.and_utc())
+}
+
+#[expect(
+    clippy::cast_possible_truncation,
+    reason = "Byte counts of a projection don't need to be exact."
+)]
+const fn to_bytes(value: f64) -> i64 {
+    value as i64
+}
+
+/// `+1.50 GB` or `-200 B`
+fn signed_size(size: i64) -> String {
+    let sign = if size < 0 { '-' } else { '+' };
+
+    format!("{sign}{}", human_size(size.saturating_abs()))
+}
+
+/// The numbers behind a row, per bucket or summed up for the footer
+#[derive(Serialize, Debug, Clone, Copy, Default)]
+struct Trend {
+    size: i64,
+    growth_per_day: Option<i64>,
+    growth_per_week: Option<i64>,
+    projected_month_end: Option<i64>,
+}
+
+impl Trend {
+    fn new(
+        samples: &[Sample],
+        until: DateTime<Utc>,
+    ) -> Self {
+        let Some(&(last_time, size)) = samples.last() else {
+            return Self::default();
+        };
+
+        let rate = growth_rate(samples);
+        let remaining = (until - last_time).num_seconds().max(0);
+
+        Self {
+            size,
+            growth_per_day: rate.map(|rate| to_bytes(rate * SECONDS_PER_DAY)),
+            growth_per_week: rate.map(|rate| to_bytes(rate * SECONDS_PER_DAY * 7.0)),
+            projected_month_end: rate.map(|rate| {
+                #[expect(
+                    clippy::cast_precision_loss,
+                    reason = "A month has far less than 2^52 seconds."
+                )]
+                let growth = rate * remaining as f64;
+                (size + to_bytes(growth)).max(0)
+            }),
+        }
+    }
+
+    /// Buckets without a trend count with their current size towards the projected total
+    fn add(
+        self,
+        other: Self,
+    ) -> Self {
+        let sum = |a: Option<i64>, b: Option<i64>| match (a, b) {
+            (None, None) => None,
+            _ => Some(a.unwrap_or_default() + b.unwrap_or_default()),
+        };
+
+        Self {
+            size: self.size + other.size,
+            growth_per_day: sum(self.growth_per_day, other.growth_per_day),
+            growth_per_week: sum(self.growth_per_week, other.growth_per_week),
+            projected_month_end: Some(
+                self.projected_month_end.unwrap_or(self.size)
+                    + other.projected_month_end.unwrap_or(other.size),
+            ),
+        }
+    }
+}
+
+/// Structured `--format`s get the raw numbers (`trend`) instead of the display strings
+#[derive(Tabled, Serialize)]
+pub struct TrendTable {
+    account_id: String,
+    bucket: String,
+    records: usize,
+    first_record: String,
+    last_record: String,
+    #[serde(skip)]
+    #[tabled(rename = "size")]
+    human_size: String,
+    #[serde(skip)]
+    growth: String,
+    #[serde(skip)]
+    projected_month_end: String,
+    #[tabled(skip)]
+    #[serde(flatten)]
+    trend: Trend,
+}
+
+fn format_date(time: Option<DateTime<Utc>>) -> String {
+    time.map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+impl TrendTable {
+    fn new(
+        account_id: String,
+        bucket: String,
+        records: usize,
+        first: Option<DateTime<Utc>>,
+        last: Option<DateTime<Utc>>,
+        trend: Trend,
+        per_week: bool,
+    ) -> Self {
+        let unknown = || String::from("?");
+        let (growth, unit) = if per_week {
+            (trend.growth_per_week, "week")
+        } else {
+            (trend.growth_per_day, "day")
+        };
+
+        Self {
+            account_id,
+            bucket,
+            records,
+            first_record: format_date(first),
+            last_record: format_date(last),
+            human_size: human_size(trend.size),
+            growth: growth.map_or_else(unknown, |growth| format!("{}/{unit}", signed_size(growth))),
+            projected_month_end: trend.projected_month_end.map_or_else(unknown, human_size),
+            trend,
+        }
+    }
+}
+
+/// One row per bucket in the history, plus a total
+fn trend_rows(
+    records: &[UsageRecord],
+    options: &UsageTrendOptions,
+    now: DateTime<Utc>,
+) -> Vec<TrendTable> {
+    let since = now - TimeDelta::seconds(i64::try_from(options.since).unwrap_or(i64::MAX));
+    let until = month_end(now);
+    let per_week = options.per == "week";
+
+    let mut buckets: BTreeMap<(&str, &str), Vec<Sample>> = BTreeMap::new();
+    for record in records {
+        let matches = options
+            .filter
+            .as_ref()
+            .is_none_or(|filter| record.bucket.contains(filter.as_str()));
+
+        if let Some(time) = record
+            .measured_at()
+            .filter(|time| matches && *time >= since)
+        {
+            buckets
+                .entry((&record.account_id, &record.bucket))
+                .or_default()
+                .push((time, record.size()));
+        }
+    }
+
+    let mut rows = Vec::new();
+    let mut total = Trend::default();
+    let (mut first_total, mut last_total, mut records_total) = (None, None, 0);
+
+    for ((account_id, bucket), mut samples) in buckets {
+        samples.sort_unstable();
+
+        let first = samples.first().map(|(time, _)| *time);
+        let last = samples.last().map(|(time, _)| *time);
+        let trend = Trend::new(&samples, until);
+
+        total = total.add(trend);
+        records_total += samples.len();
+        first_total = first_total.into_iter().chain(first).min();
+        last_total = last_total.max(last);
+
+        rows.push(TrendTable::new(
+            account_id.to_owned(),
+            bucket.to_owned(),
+            samples.len(),
+            first,
+            last,
+            trend,
+            per_week,
+        ));
+    }
+
+    // in `--format json|csv|toml`, a footer would look like just another bucket:
+    if rows.is_empty() || !OutputFormat::is_table() {
+        return rows;
+    }
+
+    rows.push(TrendTable::new(
+        String::from("all"),
+        String::from("total"),
+        records_total,
+        first_total,
+        last_total,
+        total,
+        per_week,
+    ));
+
+    rows
+}
+
+impl Process for UsageOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        self.cmd.process().await
+    }
+}
+
+impl Process for UsageRecordOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let clients = if self.all_profiles {
+            R2D2::all_profiles().await?
+        } else {
+            vec![(String::from("default"), Ok(R2D2::guess().await?))]
+        };
+
+        let recorded_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let path = history_path(self.history);
+
+        let mut records = Vec::new();
+        let mut failed = 0;
+
+        for (name, client) in clients {
+            let result = match client {
+                Ok(r2) => bucket_records(&r2, &recorded_at).await,
+                Err(error) => Err(error),
+            };
+
+            match result {
+                Ok((mut account_records, account_failed)) => {
+                    records.append(&mut account_records);
+                    failed += account_failed;
+                },
+                Err(error) => {
+                    failed += 1;
+                    eprintln!("{} `{name}` was skipped: {error}", "Warning:".yellow());
+                },
+            }
+        }
+
+        append_records(&path, &records)?;
+        eprintln!(
+            "Recorded the usage of {} bucket(s) to {}.",
+            records.len(),
+            path.display()
+        );
+
+        Ok(i32::from(failed > 0))
+    }
+}
+
+impl Process for UsageTrendOptions {
+    async fn process(self) -> anyhow::Result<i32> {
+        let path = history_path(self.history.clone());
+        let records = read_records(&path)?;
+
+        let rows = trend_rows(&records, &self, Utc::now());
+
+        if rows.is_empty() && OutputFormat::is_table() {
+            eprintln!(
+                "No usage recorded in this period, run `r2-d2 usage record` (e.g. daily) first."
+            );
+            return Ok(0);
+        }
+
+        print_table(&rows)?;
+
+        Ok(0)
+    }
+}
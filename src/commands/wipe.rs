@@ -1,5 +1,6 @@
 use crate::cli::{Process, WipeOptions};
-use crate::r2::R2D2;
+use crate::r2::{BucketLockRuleList, R2D2};
+use crate::r2_error::is_not_configured;
 use crate::r2_purge::empty_repo;
 use anyhow::bail;
 
@@ -12,7 +13,11 @@ impl Process for WipeOptions {
             todo!("Still need to find a nice library to do confirmations etc.")
         }
 
-        let mut r2 = R2D2::guess()?;
+        let mut r2 = R2D2::guess().await?;
+
+        if r2.is_append_only() {
+            bail!("`wipe` is disabled in append-only mode (`R2_APPEND_ONLY`).");
+        }
 
         if self.bucket.is_some() {
             r2.set_bucket(self.bucket);
@@ -23,11 +28,30 @@ impl Process for WipeOptions {
         };
 
         if self.include_contents {
+            // fail before deleting anything instead of halfway through a locked prefix:
+            let locks = match r2.lock_rules(Some(bucket.clone())).await {
+                Ok(locks) => locks,
+                Err(error) if is_not_configured(&error) => BucketLockRuleList::default(),
+                Err(error) => {
+                    return Err(error.context(format!(
+                        "Could not check the lock rules of `{bucket}`, nothing was deleted"
+                    )));
+                },
+            };
+            let locked: Vec<&str> = locks.enabled().map(|rule| rule.id.as_str()).collect();
+
+            if !locked.is_empty() {
+                bail!(
+                    "Bucket `{bucket}` has active lock rules ({}); remove them first with `r2-d2 lock-rules remove`.",
+                    locked.join(", ")
+                );
+            }
+
             empty_repo(&r2).await?;
         }
 
         if self.include_bucket {
-            r2.delete_bucket_py(bucket, None).await?;
+            r2.delete_bucket(bucket, None).await?.into_error()?;
 
             eprintln!("Bucket `{bucket}` deleted.");
         }
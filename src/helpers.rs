@@ -2,8 +2,13 @@ use std::future::Future;
 use std::path::PathBuf;
 use std::str::FromStr;
 
-use pyo3::exceptions::PyRuntimeError;
+use crate::output::{OutputFormat, print_rows};
+use crate::r2_error::exceptions::R2Error;
+use crate::r2_error::to_py_err;
+use byte_unit::{Byte, UnitType};
+use owo_colors::OwoColorize;
 use pyo3::{IntoPy, PyAny, PyErr, PyObject, PyResult, Python};
+use serde::Serialize;
 use tabled::settings as table;
 use tabled::settings::object::{Columns, Rows};
 use tabled::{Table, Tabled};
@@ -29,7 +34,91 @@ pub fn sotoi(ascii_option: Option<String>) -> i64 {
     stoi(ascii_option.unwrap_or_default())
 }
 
-pub fn print_table<T: Tabled>(rows: &Vec<T>) {
+/// Parse durations like `90`, `30s`, `15m`, `1h`, `7d` or `2w` into seconds.
+pub fn parse_duration(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((idx, 's')) => (&value[..idx], 1),
+        Some((idx, 'm')) => (&value[..idx], 60),
+        Some((idx, 'h')) => (&value[..idx], 60 * 60),
+        Some((idx, 'd')) => (&value[..idx], 60 * 60 * 24),
+        Some((idx, 'w')) => (&value[..idx], 60 * 60 * 24 * 7),
+        _ => (value, 1),
+    };
+
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| format!("Invalid duration `{value}` (expected e.g. `30d`, `12h` or `90s`)"))
+}
+
+/// Bytes in decimal units, e.g. `1.50 GB`
+pub fn human_size(size: i64) -> String {
+    let byte = Byte::from_i64(size)
+        .unwrap_or_default()
+        .get_appropriate_unit(UnitType::Decimal);
+
+    format!("{byte:#.2}")
+}
+
+/// Print a table without highlighting the last row (for lists without a 'total' footer)
+pub fn print_list_table<T: Tabled + Serialize>(rows: &[T]) -> anyhow::Result<()> {
+    if !OutputFormat::is_table() {
+        return print_rows(rows);
+    }
+
+    let table_config = table::Settings::default().with(table::Style::rounded());
+    let mut table = Table::new(rows);
+    table.with(table_config);
+
+    println!("{table}");
+
+    Ok(())
+}
+
+/// Print a line-based diff (longest common subsequence) of `old` and `new`
+pub fn print_diff(
+    old: &str,
+    new: &str,
+) {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] = length of the common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            println!("  {}", old[i]);
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            println!("{}", format!("- {}", old[i]).red());
+            i += 1;
+        } else {
+            println!("{}", format!("+ {}", new[j]).green());
+            j += 1;
+        }
+    }
+}
+
+pub fn print_table<T: Tabled + Serialize>(rows: &Vec<T>) -> anyhow::Result<()> {
+    if !OutputFormat::is_table() {
+        return print_rows(rows);
+    }
+
     let table_config = table::Settings::default()
         .with(table::Style::rounded())
         // .with(table::Padding::new(2, 2, 1, 1))
@@ -43,6 +132,8 @@ pub fn print_table<T: Tabled>(rows: &Vec<T>) {
     ));
 
     println!("{table}");
+
+    Ok(())
 }
 
 pub trait ResultToString<T, E> {
@@ -119,7 +210,7 @@ impl<T> IntoPythonError<T> for Result<T, String> {
         self,
         _: &str,
     ) -> PyResult<T> {
-        self.map_err(PyRuntimeError::new_err)
+        self.map_err(R2Error::new_err)
     }
 }
 
@@ -128,13 +219,13 @@ impl<T> IntoPythonError<T> for anyhow::Result<T> {
         self,
         _: &str,
     ) -> PyResult<T> {
-        self.map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        self.map_err(|e| to_py_err(&e, e.to_string()))
     }
 }
 
 impl<T> UnwrapIntoPythonError<T> for Result<T, String> {
     fn unwrap_or_raise(self) -> PyResult<T> {
-        self.map_err(PyRuntimeError::new_err)
+        self.map_err(R2Error::new_err)
     }
 }
 
@@ -144,6 +235,6 @@ pub fn fmt_error(e: &anyhow::Error) -> String {
 
 impl<T> UnwrapIntoPythonError<T> for anyhow::Result<T> {
     fn unwrap_or_raise(self) -> PyResult<T> {
-        self.map_err(|e| PyRuntimeError::new_err(fmt_error(&e)))
+        self.map_err(|e| to_py_err(&e, fmt_error(&e)))
     }
 }
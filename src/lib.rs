@@ -1,7 +1,11 @@
 use crate::cli::{Args, Process};
+use crate::commands::bucket::{R2Bucket, create_bucket};
 use crate::commands::usage::R2Usage;
 use crate::commands::usage::usage;
 use crate::helpers::{UnwrapIntoPythonError, fmt_error, future_pyresult_to_py};
+use crate::metrics::print_report;
+use crate::r2::R2D2Builder;
+use crate::r2_error::{exceptions, exit_code};
 use clap::{Command, CommandFactory, Parser};
 use clap_complete::{Generator, generate};
 use pyo3::exceptions::PyValueError;
@@ -13,7 +17,12 @@ use std::{env, io};
 pub mod cli;
 pub mod commands;
 pub mod helpers;
+pub mod metrics;
+pub mod output;
 pub mod r2;
+pub mod r2_auth_check;
+pub mod r2_doctor;
+pub mod r2_error;
 pub mod r2_purge;
 
 pub mod r2_upload;
@@ -33,6 +42,7 @@ async fn async_main_rs() -> anyhow::Result<i32> {
     // skip first (`python r2d2 subcommand` -> `r2d2 subcommand`):
 
     let args = Args::parse_from(env::args().skip(1));
+    args.format.init();
 
     let exit_code = if let Some(generator) = args.generator {
         let mut cmd = Args::command();
@@ -42,10 +52,12 @@ async fn async_main_rs() -> anyhow::Result<i32> {
     } else {
         args.cmd.process().await.unwrap_or_else(|msg| {
             eprintln!("{}", fmt_error(&msg));
-            1
+            exit_code(&msg)
         })
     };
 
+    print_report(&R2D2Builder::guess().unwrap_or_default().pricing());
+
     exit(exit_code);
 }
 
@@ -65,19 +77,42 @@ pub fn error(py: Python<'_>) -> PyResult<&PyAny> {
 pub fn main_rs(py: Python<'_>) -> PyResult<&PyAny> {
     pyo3_asyncio::tokio::future_into_py(py, async {
         // result is Ok(exit code) or Err(python error)
-        async_main_rs().await.unwrap_or_raise()
+        Box::pin(async_main_rs()).await.unwrap_or_raise()
     })
 }
 
 #[pyo::pymodule]
 pub fn r2_d2(
-    _py: Python,
+    py: Python,
     m: &PyModule,
 ) -> PyResult<()> {
     m.add_class::<R2Usage>()?;
+    m.add_class::<R2Bucket>()?;
+
+    m.add("R2Error", py.get_type::<exceptions::R2Error>())?;
+    m.add(
+        "AuthenticationError",
+        py.get_type::<exceptions::AuthenticationError>(),
+    )?;
+    m.add("NotFound", py.get_type::<exceptions::NotFound>())?;
+    m.add(
+        "BucketNotFound",
+        py.get_type::<exceptions::BucketNotFound>(),
+    )?;
+    m.add(
+        "BucketNotEmpty",
+        py.get_type::<exceptions::BucketNotEmpty>(),
+    )?;
+    m.add("RateLimited", py.get_type::<exceptions::RateLimited>())?;
+    m.add("ConfigError", py.get_type::<exceptions::ConfigError>())?;
+    m.add(
+        "RepositoryError",
+        py.get_type::<exceptions::RepositoryError>(),
+    )?;
 
     m.add_function(pyo::wrap_pyfunction!(main_rs, m)?)?;
     m.add_function(pyo::wrap_pyfunction!(usage, m)?)?;
+    m.add_function(pyo::wrap_pyfunction!(create_bucket, m)?)?;
     m.add_function(pyo::wrap_pyfunction!(error, m)?)?;
     Ok(())
 }
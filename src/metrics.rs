@@ -0,0 +1,346 @@
+use byte_unit::{Byte, UnitType};
+use opendal::raw::{
+    Access, Layer, LayeredAccess, OpDelete, OpList, OpRead, OpStat, OpWrite, RpDelete, RpList,
+    RpRead, RpStat, RpWrite, oio,
+};
+use opendal::{Buffer, Metadata, Result};
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// S3 returns at most 1000 keys per `ListObjectsV2` page.
+const LIST_PAGE_SIZE: u64 = 1000;
+
+/// Default R2 prices in USD per million requests.
+/// See: [https://developers.cloudflare.com/r2/pricing/](https://developers.cloudflare.com/r2/pricing/)
+pub const DEFAULT_PRICE_CLASS_A: f64 = 4.50;
+pub const DEFAULT_PRICE_CLASS_B: f64 = 0.36;
+
+static METRICS: OperationMetrics = OperationMetrics::new();
+
+/// Process-wide counters of the S3 operations done via opendal.
+#[derive(Debug, Default)]
+pub struct OperationMetrics {
+    lists: AtomicU64,
+    listed_entries: AtomicU64,
+    stats: AtomicU64,
+    reads: AtomicU64,
+    writes: AtomicU64,
+    deletes: AtomicU64,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
+impl OperationMetrics {
+    const fn new() -> Self {
+        Self {
+            lists: AtomicU64::new(0),
+            listed_entries: AtomicU64::new(0),
+            stats: AtomicU64::new(0),
+            reads: AtomicU64::new(0),
+            writes: AtomicU64::new(0),
+            deletes: AtomicU64::new(0),
+            bytes_read: AtomicU64::new(0),
+            bytes_written: AtomicU64::new(0),
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &METRICS
+    }
+
+    fn inc(
+        counter: &AtomicU64,
+        amount: u64,
+    ) {
+        counter.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            lists: self.lists.load(Ordering::Relaxed),
+            listed_entries: self.listed_entries.load(Ordering::Relaxed),
+            stats: self.stats.load(Ordering::Relaxed),
+            reads: self.reads.load(Ordering::Relaxed),
+            writes: self.writes.load(Ordering::Relaxed),
+            deletes: self.deletes.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MetricsSnapshot {
+    pub lists: u64,
+    pub listed_entries: u64,
+    pub stats: u64,
+    pub reads: u64,
+    pub writes: u64,
+    pub deletes: u64,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+
+impl MetricsSnapshot {
+    /// Listing is billed per page, so long listings cost more than one operation.
+    pub const fn list_requests(&self) -> u64 {
+        let pages = self.listed_entries.div_ceil(LIST_PAGE_SIZE);
+        if pages > self.lists {
+            pages
+        } else {
+            self.lists
+        }
+    }
+
+    /// Mutating operations (`PUT`, multipart parts, `LIST`)
+    pub const fn class_a(&self) -> u64 {
+        self.list_requests() + self.writes
+    }
+
+    /// Reading operations (`GET`, `HEAD`)
+    pub const fn class_b(&self) -> u64 {
+        self.stats + self.reads
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.lists + self.stats + self.reads + self.writes + self.deletes == 0
+    }
+
+    /// Estimated cost in USD (`DELETE` is free on R2)
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "Operation counts stay far below 2^52."
+    )]
+    pub fn cost(
+        &self,
+        pricing: &OperationPricing,
+    ) -> f64 {
+        (self.class_a() as f64).mul_add(
+            pricing.class_a / 1_000_000.0,
+            self.class_b() as f64 * pricing.class_b / 1_000_000.0,
+        )
+    }
+}
+
+fn human_bytes(bytes: u64) -> String {
+    let byte = Byte::from_u64(bytes).get_appropriate_unit(UnitType::Decimal);
+    format!("{byte:#.2}")
+}
+
+impl Display for MetricsSnapshot {
+    fn fmt(
+        &self,
+        f: &mut Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(
+            f,
+            "{} Class A (list {}, write {}), {} Class B (stat {}, read {}), {} delete; {} up, {} down",
+            self.class_a(),
+            self.list_requests(),
+            self.writes,
+            self.class_b(),
+            self.stats,
+            self.reads,
+            self.deletes,
+            human_bytes(self.bytes_written),
+            human_bytes(self.bytes_read),
+        )
+    }
+}
+
+/// Prices in USD per million requests (`R2_PRICE_CLASS_A`, `R2_PRICE_CLASS_B`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OperationPricing {
+    pub class_a: f64,
+    pub class_b: f64,
+}
+
+impl Default for OperationPricing {
+    fn default() -> Self {
+        Self {
+            class_a: DEFAULT_PRICE_CLASS_A,
+            class_b: DEFAULT_PRICE_CLASS_B,
+        }
+    }
+}
+
+/// Print the operations done during this command and their estimated cost
+pub fn print_report(pricing: &OperationPricing) {
+    let snapshot = OperationMetrics::global().snapshot();
+
+    if snapshot.is_empty() {
+        return;
+    }
+
+    eprintln!(
+        "R2 operations: {snapshot}; estimated cost ${:.6}",
+        snapshot.cost(pricing)
+    );
+}
+
+/// opendal layer that counts every operation into `OperationMetrics::global()`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsLayer;
+
+impl<A: Access> Layer<A> for MetricsLayer {
+    type LayeredAccess = MetricsAccessor<A>;
+
+    fn layer(
+        &self,
+        inner: A,
+    ) -> Self::LayeredAccess {
+        MetricsAccessor { inner }
+    }
+}
+
+#[derive(Debug)]
+pub struct MetricsAccessor<A: Access> {
+    inner: A,
+}
+
+impl<A: Access> LayeredAccess for MetricsAccessor<A> {
+    type Inner = A;
+    type Reader = MetricsWrapper<A::Reader>;
+    type Writer = MetricsWrapper<A::Writer>;
+    type Lister = MetricsWrapper<A::Lister>;
+    type Deleter = MetricsWrapper<A::Deleter>;
+
+    fn inner(&self) -> &Self::Inner {
+        &self.inner
+    }
+
+    async fn read(
+        &self,
+        path: &str,
+        args: OpRead,
+    ) -> Result<(RpRead, Self::Reader)> {
+        OperationMetrics::inc(&METRICS.reads, 1);
+
+        self.inner
+            .read(path, args)
+            .await
+            .map(|(rp, reader)| (rp, MetricsWrapper::new(reader)))
+    }
+
+    async fn write(
+        &self,
+        path: &str,
+        args: OpWrite,
+    ) -> Result<(RpWrite, Self::Writer)> {
+        self.inner
+            .write(path, args)
+            .await
+            .map(|(rp, writer)| (rp, MetricsWrapper::new(writer)))
+    }
+
+    async fn stat(
+        &self,
+        path: &str,
+        args: OpStat,
+    ) -> Result<RpStat> {
+        OperationMetrics::inc(&METRICS.stats, 1);
+
+        self.inner.stat(path, args).await
+    }
+
+    async fn delete(&self) -> Result<(RpDelete, Self::Deleter)> {
+        self.inner
+            .delete()
+            .await
+            .map(|(rp, deleter)| (rp, MetricsWrapper::new(deleter)))
+    }
+
+    async fn list(
+        &self,
+        path: &str,
+        args: OpList,
+    ) -> Result<(RpList, Self::Lister)> {
+        OperationMetrics::inc(&METRICS.lists, 1);
+
+        self.inner
+            .list(path, args)
+            .await
+            .map(|(rp, lister)| (rp, MetricsWrapper::new(lister)))
+    }
+}
+
+pub struct MetricsWrapper<R> {
+    inner: R,
+    /// Chunks written so far, more than one means a multipart upload
+    chunks: u64,
+}
+
+impl<R> MetricsWrapper<R> {
+    const fn new(inner: R) -> Self {
+        Self { inner, chunks: 0 }
+    }
+}
+
+impl<R: oio::Read> oio::Read for MetricsWrapper<R> {
+    async fn read(&mut self) -> Result<Buffer> {
+        let buffer = self.inner.read().await?;
+        OperationMetrics::inc(&METRICS.bytes_read, buffer.len() as u64);
+
+        Ok(buffer)
+    }
+}
+
+impl<W: oio::Write> oio::Write for MetricsWrapper<W> {
+    async fn write(
+        &mut self,
+        bs: Buffer,
+    ) -> Result<()> {
+        // every chunk is a `PUT` (or a multipart `UploadPart`)
+        OperationMetrics::inc(&METRICS.writes, 1);
+        OperationMetrics::inc(&METRICS.bytes_written, bs.len() as u64);
+
+        // a second chunk means the upload is multipart, which starts with `CreateMultipartUpload`:
+        self.chunks += 1;
+        if self.chunks == 2 {
+            OperationMetrics::inc(&METRICS.writes, 1);
+        }
+
+        self.inner.write(bs).await
+    }
+
+    async fn abort(&mut self) -> Result<()> {
+        self.inner.abort().await
+    }
+
+    async fn close(&mut self) -> Result<Metadata> {
+        // ... and ends with `CompleteMultipartUpload`
+        if self.chunks > 1 {
+            OperationMetrics::inc(&METRICS.writes, 1);
+        }
+
+        self.inner.close().await
+    }
+}
+
+impl<L: oio::List> oio::List for MetricsWrapper<L> {
+    async fn next(&mut self) -> Result<Option<oio::Entry>> {
+        let entry = self.inner.next().await?;
+
+        if entry.is_some() {
+            OperationMetrics::inc(&METRICS.listed_entries, 1);
+        }
+
+        Ok(entry)
+    }
+}
+
+impl<D: oio::Delete> oio::Delete for MetricsWrapper<D> {
+    fn delete(
+        &mut self,
+        path: &str,
+        args: OpDelete,
+    ) -> Result<()> {
+        OperationMetrics::inc(&METRICS.deletes, 1);
+
+        self.inner.delete(path, args)
+    }
+
+    async fn flush(&mut self) -> Result<usize> {
+        self.inner.flush().await
+    }
+}
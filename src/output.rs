@@ -0,0 +1,121 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// Global `--format` of the CLI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable tables and messages
+    #[default]
+    Table,
+    Json,
+    Csv,
+    Toml,
+}
+
+impl OutputFormat {
+    /// Set once at startup, later calls are ignored
+    pub fn init(self) {
+        let _ = FORMAT.set(self);
+    }
+
+    pub fn current() -> Self {
+        FORMAT.get().copied().unwrap_or_default()
+    }
+
+    pub fn is_table() -> bool {
+        Self::current() == Self::Table
+    }
+}
+
+/// TOML documents need a table at the top level
+#[derive(Serialize)]
+struct TomlRows<'a, T> {
+    rows: &'a [T],
+}
+
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// Header from the keys of all rows (in order of appearance), missing fields stay empty
+fn to_csv<T: Serialize>(rows: &[T]) -> anyhow::Result<String> {
+    let rows: Vec<Value> = rows
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<Result<_, _>>()?;
+
+    let mut keys: Vec<&String> = Vec::new();
+    for key in rows
+        .iter()
+        .filter_map(Value::as_object)
+        .flat_map(|row| row.keys())
+    {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    if keys.is_empty() {
+        return Ok(String::new());
+    }
+
+    let mut csv = keys
+        .iter()
+        .map(|key| csv_field(&Value::String((*key).clone())))
+        .collect::<Vec<_>>()
+        .join(",");
+    csv.push('\n');
+
+    for row in &rows {
+        let line = keys
+            .iter()
+            .map(|key| csv_field(row.get(key.as_str()).unwrap_or(&Value::Null)))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        csv.push_str(&line);
+        csv.push('\n');
+    }
+
+    Ok(csv)
+}
+
+/// Print rows in a machine-readable `--format` (not `table`)
+pub fn print_rows<T: Serialize>(rows: &[T]) -> anyhow::Result<()> {
+    match OutputFormat::current() {
+        OutputFormat::Json | OutputFormat::Table => {
+            println!("{}", serde_json::to_string_pretty(rows)?);
+        },
+        OutputFormat::Csv => print!("{}", to_csv(rows)?),
+        OutputFormat::Toml => print!("{}", toml::to_string(&TomlRows { rows })?),
+    }
+
+    Ok(())
+}
+
+/// Print a single object in a machine-readable `--format` (not `table`)
+pub fn print_record<T: Serialize>(record: &T) -> anyhow::Result<()> {
+    match OutputFormat::current() {
+        OutputFormat::Json | OutputFormat::Table => {
+            println!("{}", serde_json::to_string_pretty(record)?);
+        },
+        OutputFormat::Csv => print!("{}", to_csv(std::slice::from_ref(record))?),
+        OutputFormat::Toml => print!("{}", toml::to_string(record)?),
+    }
+
+    Ok(())
+}
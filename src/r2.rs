@@ -1,27 +1,42 @@
+use crate::commands::bucket::CreateBucketOptions;
 use crate::commands::list::ListOptions;
 use crate::commands::wipe::DeleteOptions;
-use crate::helpers::IntoPythonError;
-use crate::rustic_backends::r2_backend::R2Backend;
+use crate::helpers::{IntoPythonError, parse_duration};
+use crate::metrics::OperationPricing;
+use crate::r2_error::{R2Error, is_not_configured, to_py_err};
+use crate::rustic_backends::r2_backend::{BackendKind, R2Backend};
 use crate::rustic_progress::ProgressBar;
 use anyhow::{Context, anyhow, bail};
+use chrono::{DateTime, TimeDelta, Utc};
 use dotenvy::from_path_iter;
 use opendal::Operator;
+use owo_colors::OwoColorize;
 use pyo3::PyResult;
-use pyo3::exceptions::{PyRuntimeError, PyValueError};
-use reqwest::header::HeaderMap;
-use reqwest::{Client, RequestBuilder};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{
+    Certificate, Client, ClientBuilder, Method, Proxy, RequestBuilder, Response, StatusCode,
+};
 use resolve_path::PathResolveExt;
 use rustic_core::{Repository, RepositoryOptions};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
 use std::env;
 use std::fmt::{Debug, Display, Formatter};
+use std::fs;
+use std::hash::{Hash, Hasher};
 use std::io::BufReader;
 use std::ops::{BitAnd, BitOr};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use url::Url;
 
+/// Default for `R2_API_URL`
 const CLOUDFLARE_API: &str = "https://api.cloudflare.com/client/v4/";
+const USER_AGENT: &str = concat!("r2-d2/", env!("CARGO_PKG_VERSION"));
+/// Default for `R2_PROFILES_DIR`
+const PROFILES_DIR: &str = "~/.config/r2-d2/profiles";
 
 fn get_from_config(
     config: &BTreeMap<String, String>,
@@ -37,6 +52,25 @@ pub fn get_from_env(key: &str) -> anyhow::Result<String> {
     env::var(key).map_err(|_| anyhow!("Key {key} could not be found in your environment."))
 }
 
+/// Interpret config values like `1`, `true`, `yes` or `on` as enabled.
+fn parse_flag(value: &str) -> bool {
+    matches!(
+        value.trim().to_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+/// R2 jurisdictions (`R2_JURISDICTION`); `default` means no jurisdiction.
+fn parse_jurisdiction(value: &str) -> anyhow::Result<Option<String>> {
+    match value.trim().to_lowercase().as_str() {
+        "" | "default" => Ok(None),
+        jurisdiction @ ("eu" | "fedramp") => Ok(Some(jurisdiction.to_owned())),
+        _ => bail!(
+            "Unknown jurisdiction `{value}` in `R2_JURISDICTION` (expected `eu`, `fedramp` or `default`)."
+        ),
+    }
+}
+
 fn read_configfile(path: &PathBuf) -> Option<BTreeMap<String, String>> {
     let iter = from_path_iter(path).ok()?;
 
@@ -62,10 +96,45 @@ pub struct ApiResponse<T> {
     // pub messages: Option<Vec<String>>,
     pub messages: Option<Vec<Message>>,
     pub result: Option<T>,
+    pub result_info: Option<ResultInfo>,
+    /// HTTP status of the response, not part of the body
+    #[serde(skip)]
+    pub status: Option<u16>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ResultInfo {
+    /// Set when there are more pages to fetch
+    pub cursor: Option<String>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    /// Items in this page
+    pub count: Option<u64>,
+    pub total_count: Option<u64>,
+    pub total_pages: Option<u32>,
 }
 
 impl<T> ApiResponse<T> {
-    fn into_error(self) -> anyhow::Result<T> {
+    /// Print `messages` (e.g. deprecation notices), which are easy to miss otherwise
+    fn report_messages(&self) {
+        for message in self.messages.iter().flatten() {
+            if message.message.trim().is_empty() {
+                continue;
+            }
+
+            let code = message
+                .code
+                .map_or_else(String::new, |code| format!(" (code {code})"));
+
+            eprintln!(
+                "{} {}{code}",
+                "Cloudflare API:".yellow(),
+                message.message.trim()
+            );
+        }
+    }
+
+    pub fn into_error(self) -> anyhow::Result<T> {
         if self.success {
             return self.result.map_or_else(
                 || Err(anyhow!("Expected result data but got None!",)),
@@ -73,23 +142,26 @@ impl<T> ApiResponse<T> {
             );
         }
 
-        Err(self.errors.as_ref().map_or_else(
-            || anyhow!("Something went wrong, but no specific information was provided."),
-            |errors| {
-                if errors.is_empty() {
-                    anyhow!("Something went wrong, but no specific information was provided.")
-                } else {
-                    let mut msg = String::new();
+        let errors = self.errors.unwrap_or_default();
 
-                    for error in errors {
-                        msg.push_str(&error.message);
-                        msg.push('\n');
-                    }
+        if errors.is_empty() {
+            return Err(R2Error::from_api(
+                None,
+                self.status,
+                "Something went wrong, but no specific information was provided.",
+            )
+            .into());
+        }
 
-                    anyhow!("{msg}")
-                }
-            },
-        ))
+        let mut msg = String::new();
+
+        for error in &errors {
+            msg.push_str(&error.message);
+            msg.push('\n');
+        }
+
+        // the first error decides the kind, the others are usually follow-ups:
+        Err(R2Error::from_api(errors.first().map(|error| error.code), self.status, msg).into())
     }
 }
 
@@ -98,40 +170,8 @@ impl<T> IntoPythonError<T> for ApiResponse<T> {
         self,
         hint: &str,
     ) -> PyResult<T> {
-        if self.success {
-            return self.result.map_or_else(
-                || {
-                    Err(PyRuntimeError::new_err(
-                        "Expected result data but got None!",
-                    ))
-                },
-                |data| Ok(data),
-            );
-        }
-
-        Err(self.errors.as_ref().map_or_else(
-            || {
-                PyValueError::new_err(format!(
-                    "Something went wrong for {hint}, but no specific information was provided."
-                ))
-            },
-            |errors| {
-                if errors.is_empty() {
-                    PyValueError::new_err(format!(
-                        "Something went wrong for {hint}, but no specific information was provided."
-                    ))
-                } else {
-                    let mut msg = format!("[{hint}] ");
-
-                    for error in errors {
-                        msg.push_str(&error.message);
-                        msg.push('\n');
-                    }
-
-                    PyValueError::new_err(msg)
-                }
-            },
-        ))
+        self.into_error()
+            .map_err(|e| to_py_err(&e, format!("[{hint}] {e}")))
     }
 }
 
@@ -201,12 +241,235 @@ pub struct CustomBucketDomainData {
     pub domain: String,
     pub enabled: bool,
     pub status: Option<CustomBucketDomainStatus>,
-    // minDNS, zoneId, zoneName
+    #[serde(rename = "minTLS")]
+    pub min_tls: Option<String>,
+    #[serde(rename = "zoneId")]
+    pub zone_id: Option<String>,
+    #[serde(rename = "zoneName")]
+    pub zone_name: Option<String>,
 }
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CustomBucketDomainStatus {
-    ownership: String,
-    ssl: String,
+    pub ownership: String,
+    pub ssl: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AttachCustomDomainRequest {
+    pub domain: String,
+    #[serde(rename = "zoneId")]
+    pub zone_id: String,
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct BucketLockRuleList {
+    pub rules: Vec<BucketLockRule>,
+}
+
+impl BucketLockRuleList {
+    pub fn enabled(&self) -> impl Iterator<Item = &BucketLockRule> {
+        self.rules.iter().filter(|rule| rule.enabled)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BucketLockRule {
+    pub id: String,
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    pub condition: BucketLockCondition,
+}
+
+impl BucketLockRule {
+    /// Whether this rule still protects `path` (last modified at `modified`) from deletion.
+    /// Anything that can't be decided counts as locked.
+    pub fn locks(
+        &self,
+        path: &str,
+        modified: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> bool {
+        if !self.enabled || !path.starts_with(self.prefix.as_deref().unwrap_or_default()) {
+            return false;
+        }
+
+        match &self.condition {
+            BucketLockCondition::Indefinite => true,
+            BucketLockCondition::Date { date } => {
+                DateTime::parse_from_rfc3339(date).map_or(true, |date| now < date)
+            },
+            BucketLockCondition::Age { max_age_seconds } => {
+                let expires = i64::try_from(*max_age_seconds)
+                    .ok()
+                    .and_then(TimeDelta::try_seconds)
+                    .zip(modified)
+                    .and_then(|(max_age, modified)| modified.checked_add_signed(max_age));
+
+                expires.is_none_or(|expires| now < expires)
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(tag = "type")]
+pub enum BucketLockCondition {
+    Age {
+        #[serde(rename = "maxAgeSeconds")]
+        max_age_seconds: u64,
+    },
+    Date {
+        date: String,
+    },
+    Indefinite,
+}
+
+impl Display for BucketLockCondition {
+    fn fmt(
+        &self,
+        f: &mut Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::Age { max_age_seconds } => write!(f, "age < {max_age_seconds}s"),
+            Self::Date { date } => write!(f, "until {date}"),
+            Self::Indefinite => write!(f, "indefinite"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct BucketCorsPolicy {
+    #[serde(default)]
+    pub rules: Vec<CorsRule>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CorsRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub allowed: CorsAllowed,
+    #[serde(
+        rename = "exposeHeaders",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub expose_headers: Vec<String>,
+    #[serde(
+        rename = "maxAgeSeconds",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_age_seconds: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CorsAllowed {
+    pub origins: Vec<String>,
+    pub methods: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct BucketLifecyclePolicy {
+    #[serde(default)]
+    pub rules: Vec<LifecycleRule>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LifecycleRule {
+    pub id: String,
+    pub enabled: bool,
+    #[serde(default)]
+    pub conditions: LifecycleRuleConditions,
+    #[serde(
+        rename = "deleteObjectsTransition",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub delete_objects_transition: Option<LifecycleTransition>,
+    #[serde(
+        rename = "abortMultipartUploadsTransition",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub abort_multipart_uploads_transition: Option<LifecycleTransition>,
+    #[serde(
+        rename = "storageClassTransitions",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub storage_class_transitions: Vec<StorageClassTransition>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct LifecycleRuleConditions {
+    #[serde(default)]
+    pub prefix: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LifecycleTransition {
+    pub condition: LifecycleCondition,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StorageClassTransition {
+    pub condition: LifecycleCondition,
+    #[serde(rename = "storageClass")]
+    pub storage_class: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(tag = "type")]
+pub enum LifecycleCondition {
+    Age {
+        #[serde(rename = "maxAge")]
+        max_age: u64,
+    },
+    Date {
+        date: String,
+    },
+}
+
+impl Display for LifecycleCondition {
+    fn fmt(
+        &self,
+        f: &mut Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::Age { max_age } => write!(f, "after {max_age}s"),
+            Self::Date { date } => write!(f, "on {date}"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TempCredentialsRequest {
+    pub bucket: String,
+    #[serde(rename = "parentAccessKeyId")]
+    pub parent_access_key_id: String,
+    /// `admin-read-write`, `admin-read-only`, `object-read-write` or `object-read-only`
+    pub permission: String,
+    #[serde(rename = "ttlSeconds")]
+    pub ttl_seconds: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prefixes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub objects: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TempCredentialsData {
+    #[serde(rename = "accessKeyId")]
+    pub access_key_id: String,
+    #[serde(rename = "secretAccessKey")]
+    pub secret_access_key: String,
+    #[serde(rename = "sessionToken")]
+    pub session_token: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -221,10 +484,16 @@ impl TokenVerifyData {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AccountData {
+    pub id: String,
+    pub name: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Message {
     #[serde(rename = "code")]
-    pub code: u32,
+    pub code: Option<u32>,
 
     #[serde(rename = "message")]
     pub message: String,
@@ -234,39 +503,146 @@ pub struct Message {
 }
 
 trait SendAndHandle {
-    async fn send_and_handle(self) -> anyhow::Result<String>;
+    /// HTTP status and body of the response
+    async fn send_and_handle(self) -> anyhow::Result<(u16, String)>;
     async fn send_and_parse<T: serde::de::DeserializeOwned>(self)
     -> anyhow::Result<ApiResponse<T>>;
 }
 
+/// How often a rate limited (429) or failed (5xx) request is sent before giving up
+const MAX_ATTEMPTS: u32 = 5;
+/// First backoff for 5xx responses, doubled on every retry
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+/// Upper bound for both the backoff and `Retry-After`
+const MAX_RETRY_DELAY: Duration = Duration::from_mins(1);
+
+/// Delay before retrying `resp`, or `None` if it shouldn't be retried.
+/// A 5xx may come after the change was made, so only idempotent requests are retried then;
+/// a 429 means the request was refused and is safe to send again for every method.
+fn retry_delay(
+    resp: &Response,
+    attempt: u32,
+    idempotent: bool,
+) -> Option<Duration> {
+    let status = resp.status();
+
+    let delay = if status == StatusCode::TOO_MANY_REQUESTS {
+        // `Retry-After` can also be an HTTP date, fall back to the backoff then:
+        resp.headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map_or_else(|| RETRY_BACKOFF * 2_u32.pow(attempt), Duration::from_secs)
+    } else if status.is_server_error() && idempotent {
+        RETRY_BACKOFF * 2_u32.pow(attempt)
+    } else {
+        return None;
+    };
+
+    Some(delay.min(MAX_RETRY_DELAY))
+}
+
+/// Describe a body that isn't an API response, like the HTML error page of a proxy
+fn unexpected_response(
+    status: u16,
+    body: &str,
+) -> String {
+    let reason = StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or_default();
+
+    // HTML pages usually say the most in their <title>:
+    let title = body
+        .split_once("<title>")
+        .and_then(|(_, rest)| rest.split_once("</title>"))
+        .map(|(title, _)| title);
+
+    let summary: String = title
+        .or_else(|| body.lines().map(str::trim).find(|line| !line.is_empty()))
+        .unwrap_or("empty body")
+        .trim()
+        .chars()
+        .take(200)
+        .collect();
+
+    format!("Unexpected response from the Cloudflare API (HTTP {status} {reason}): {summary}")
+}
+
 impl SendAndHandle for RequestBuilder {
-    async fn send_and_handle(self) -> anyhow::Result<String> {
-        let resp = self.send().await;
+    async fn send_and_handle(self) -> anyhow::Result<(u16, String)> {
+        let mut attempt = 0;
+        // e.g. a POST that creates a bucket or a token must not be sent twice:
+        let idempotent = self
+            .try_clone()
+            .and_then(|request| request.build().ok())
+            .is_some_and(|request| {
+                matches!(
+                    *request.method(),
+                    Method::GET | Method::PUT | Method::DELETE
+                )
+            });
+
+        let resp = loop {
+            // requests with a streaming body can't be cloned, and thus not be retried:
+            let Some(retry) = self.try_clone() else {
+                break self.send().await;
+            };
+
+            let resp = retry.send().await;
+            attempt += 1;
+
+            let delay = match &resp {
+                Ok(resp) if attempt < MAX_ATTEMPTS => retry_delay(resp, attempt - 1, idempotent),
+                _ => None,
+            };
+
+            match delay {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => break resp,
+            }
+        };
 
         match resp {
-            Ok(resp) => resp
-                .text()
-                .await
-                .with_context(|| "Error reading response text"),
-            Err(e) => Err(e).with_context(|| "Request error"),
+            Ok(resp) => {
+                let status = resp.status().as_u16();
+                let text = resp.text().await.map_err(|e| {
+                    anyhow::Error::new(e).context(R2Error::network("Error reading response text"))
+                })?;
+
+                Ok((status, text))
+            },
+            Err(e) => Err(anyhow::Error::new(e).context(R2Error::network("Request error"))),
         }
     }
 
     async fn send_and_parse<T: serde::de::DeserializeOwned>(
         self
     ) -> anyhow::Result<ApiResponse<T>> {
-        let text = self.send_and_handle().await?;
+        let (status, text) = self.send_and_handle().await?;
         let buffer = BufReader::new(text.as_bytes());
         // normally, `serde_json::from_string` expects &str but that requires a lifetime
         // and `text` doesn't live that long.
         // So using `serde owned` with a buffer works better in this scenario.
-        let result: ApiResponse<T> = serde_json::de::from_reader(buffer)?;
+        let mut result: ApiResponse<T> = serde_json::de::from_reader(buffer).map_err(|e| {
+            // only a body that isn't JSON at all (e.g. a proxy's error page) is summarized,
+            // a schema mismatch keeps the real decode error:
+            let message = if serde_json::from_str::<serde_json::Value>(&text).is_err() {
+                unexpected_response(status, &text)
+            } else {
+                format!("Could not decode the Cloudflare API response (HTTP {status}): {e}")
+            };
+
+            anyhow::Error::new(e).context(R2Error::from_api(None, Some(status), message))
+        })?;
+        result.status = Some(status);
+        result.report_messages();
 
         Ok(result)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Direction {
     ASC,
     DESC,
@@ -306,7 +682,10 @@ pub fn to_query_part<T: Into<String>>(
     key: &str,
     value: T,
 ) -> String {
-    format!("{}={}", key, value.into())
+    let value: String = value.into();
+    let encoded: String = url::form_urlencoded::byte_serialize(value.as_bytes()).collect();
+
+    format!("{key}={encoded}")
 }
 
 pub type ResticRepository = Repository<ProgressBar, ()>;
@@ -318,7 +697,19 @@ pub struct R2D2Builder {
     apikey: Option<String>,
     aws_access_key_id: Option<String>,
     aws_secret_access_key: Option<String>,
+    aws_session_token: Option<String>,
     bucket: Option<String>,
+    append_only: Option<bool>,
+    price_class_a: Option<String>,
+    price_class_b: Option<String>,
+    backend: Option<BackendKind>,
+    jurisdiction: Option<String>,
+    endpoint: Option<String>,
+    strict_listing: Option<bool>,
+    api_url: Option<String>,
+    http_timeout: Option<u64>,
+    https_proxy: Option<String>,
+    ca_bundle: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
@@ -327,10 +718,125 @@ pub struct R2D2 {
     apikey: String,
     aws_access_key_id: Option<String>,
     aws_secret_access_key: Option<String>,
+    /// Only for temporary credentials (`r2-d2 temp-creds`)
+    aws_session_token: Option<String>,
     pub bucket: Option<String>,
+    /// Refuse deletes so a compromised host can't destroy history.
+    #[serde(default)]
+    append_only: bool,
+    #[serde(default)]
+    backend: BackendKind,
+    /// `eu` or `fedramp`, selects `<account>.<jurisdiction>.r2.cloudflarestorage.com`
+    jurisdiction: Option<String>,
+    /// Custom S3 endpoint (e.g. `MinIO` or a local emulator), overrides `jurisdiction`
+    endpoint: Option<String>,
+    /// Fail on unexpected files in the repository instead of skipping them
+    #[serde(default)]
+    strict_listing: bool,
+    /// Cloudflare API base url, defaults to `CLOUDFLARE_API`
+    api_url: Option<String>,
+    /// Timeout for a whole API request in seconds
+    http_timeout: Option<u64>,
+    https_proxy: Option<String>,
+    /// PEM file with extra root certificates (e.g. of an intercepting proxy)
+    ca_bundle: Option<String>,
+    /// Shared by all API requests so connections are reused
+    #[serde(skip)]
+    client: HttpClient,
+    /// Enabled lock rules of the bucket, see `with_lock_rules`
+    #[serde(skip)]
+    lock_rules: Vec<BucketLockRule>,
     // todo: repo password
 }
 
+/// `reqwest::Client` is a handle to a connection pool, so it is left out of comparisons.
+#[derive(Debug, Clone, Default)]
+struct HttpClient(Client);
+
+/// `HTTPS_PROXY` and `R2_CA_BUNDLE`, shared by the API client and the S3 backend
+fn client_builder(
+    https_proxy: Option<&str>,
+    ca_bundle: Option<&str>,
+) -> anyhow::Result<ClientBuilder> {
+    let mut builder = Client::builder();
+
+    // without `HTTPS_PROXY`, reqwest still picks up the proxy environment variables:
+    if let Some(proxy) = https_proxy {
+        let proxy = Proxy::https(proxy).with_context(|| format!("Invalid proxy `{proxy}`"))?;
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(path) = ca_bundle {
+        let pem = fs::read(path.resolve())
+            .with_context(|| format!("Could not read `R2_CA_BUNDLE` ({path})"))?;
+
+        for certificate in Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Invalid certificates in `R2_CA_BUNDLE` ({path})"))?
+        {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    Ok(builder)
+}
+
+impl HttpClient {
+    fn try_new(
+        timeout: Option<u64>,
+        https_proxy: Option<&str>,
+        ca_bundle: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let mut builder = client_builder(https_proxy, ca_bundle)?.user_agent(USER_AGENT);
+
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(Duration::from_secs(timeout));
+        }
+
+        let client = builder
+            .build()
+            .context("Could not set up the HTTP client")?;
+
+        Ok(Self(client))
+    }
+}
+
+impl PartialEq for HttpClient {
+    fn eq(
+        &self,
+        _: &Self,
+    ) -> bool {
+        true
+    }
+}
+
+impl Eq for HttpClient {}
+
+impl PartialOrd for HttpClient {
+    fn partial_cmp(
+        &self,
+        other: &Self,
+    ) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HttpClient {
+    fn cmp(
+        &self,
+        _: &Self,
+    ) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+impl Hash for HttpClient {
+    fn hash<H: Hasher>(
+        &self,
+        _: &mut H,
+    ) {
+    }
+}
+
 macro_rules! bucket_request {
     ($self:expr, $bucket:expr) => {{
         let bucket = $self.bucket_or(&$bucket)?;
@@ -377,7 +883,19 @@ impl BitAnd for R2D2Builder {
             apikey: rhs.apikey.or(self.apikey),
             aws_access_key_id: rhs.aws_access_key_id.or(self.aws_access_key_id),
             aws_secret_access_key: rhs.aws_secret_access_key.or(self.aws_secret_access_key),
+            aws_session_token: rhs.aws_session_token.or(self.aws_session_token),
             bucket: rhs.bucket.or(self.bucket),
+            append_only: rhs.append_only.or(self.append_only),
+            price_class_a: rhs.price_class_a.or(self.price_class_a),
+            price_class_b: rhs.price_class_b.or(self.price_class_b),
+            backend: rhs.backend.or(self.backend),
+            jurisdiction: rhs.jurisdiction.or(self.jurisdiction),
+            endpoint: rhs.endpoint.or(self.endpoint),
+            strict_listing: rhs.strict_listing.or(self.strict_listing),
+            api_url: rhs.api_url.or(self.api_url),
+            http_timeout: rhs.http_timeout.or(self.http_timeout),
+            https_proxy: rhs.https_proxy.or(self.https_proxy),
+            ca_bundle: rhs.ca_bundle.or(self.ca_bundle),
         }
     }
 }
@@ -395,7 +913,19 @@ impl BitOr for R2D2Builder {
             apikey: self.apikey.or(rhs.apikey),
             aws_access_key_id: self.aws_access_key_id.or(rhs.aws_access_key_id),
             aws_secret_access_key: self.aws_secret_access_key.or(rhs.aws_secret_access_key),
+            aws_session_token: self.aws_session_token.or(rhs.aws_session_token),
             bucket: self.bucket.or(rhs.bucket),
+            append_only: self.append_only.or(rhs.append_only),
+            price_class_a: self.price_class_a.or(rhs.price_class_a),
+            price_class_b: self.price_class_b.or(rhs.price_class_b),
+            backend: self.backend.or(rhs.backend),
+            jurisdiction: self.jurisdiction.or(rhs.jurisdiction),
+            endpoint: self.endpoint.or(rhs.endpoint),
+            strict_listing: self.strict_listing.or(rhs.strict_listing),
+            api_url: self.api_url.or(rhs.api_url),
+            http_timeout: self.http_timeout.or(rhs.http_timeout),
+            https_proxy: self.https_proxy.or(rhs.https_proxy),
+            ca_bundle: self.ca_bundle.or(rhs.ca_bundle),
         }
     }
 }
@@ -413,9 +943,36 @@ impl R2D2Builder {
                 bucket: get_from_config(&config, "R2_BUCKET").ok(),
                 aws_access_key_id: get_from_config(&config, "R2_ACCESS_KEY_ID").ok(),
                 aws_secret_access_key: get_from_config(&config, "R2_SECRET_ACCESS_KEY").ok(),
+                aws_session_token: get_from_config(&config, "R2_SESSION_TOKEN").ok(),
+                append_only: get_from_config(&config, "R2_APPEND_ONLY")
+                    .ok()
+                    .map(|value| parse_flag(&value)),
+                price_class_a: get_from_config(&config, "R2_PRICE_CLASS_A").ok(),
+                price_class_b: get_from_config(&config, "R2_PRICE_CLASS_B").ok(),
+                backend: get_from_config(&config, "R2_BACKEND")
+                    .ok()
+                    .map(|value| value.parse())
+                    .transpose()?,
+                jurisdiction: get_from_config(&config, "R2_JURISDICTION")
+                    .ok()
+                    .map(|value| parse_jurisdiction(&value))
+                    .transpose()?
+                    .flatten(),
+                endpoint: get_from_config(&config, "R2_ENDPOINT").ok(),
+                strict_listing: get_from_config(&config, "R2_STRICT_LISTING")
+                    .ok()
+                    .map(|value| parse_flag(&value)),
+                api_url: get_from_config(&config, "R2_API_URL").ok(),
+                http_timeout: get_from_config(&config, "R2_HTTP_TIMEOUT")
+                    .ok()
+                    .map(|value| parse_duration(&value))
+                    .transpose()
+                    .map_err(|error| anyhow!("`R2_HTTP_TIMEOUT`: {error}"))?,
+                https_proxy: get_from_config(&config, "HTTPS_PROXY").ok(),
+                ca_bundle: get_from_config(&config, "R2_CA_BUNDLE").ok(),
             })
         } else {
-            bail!("Invalid config file {}", ".r2")
+            bail!("Invalid config file {}", abs_path.display())
         }
     }
 
@@ -424,17 +981,36 @@ impl R2D2Builder {
         Self::from_path(path)
     }
 
+    fn file_exists(filename: &str) -> bool {
+        Path::new(filename)
+            .try_resolve()
+            .is_ok_and(|path| path.exists())
+    }
+
+    /// Like `from_filename`, but a file that doesn't exist is an empty config instead of an error
+    fn from_optional_file(filename: &str) -> anyhow::Result<Self> {
+        if Self::file_exists(filename) {
+            Self::from_filename(filename).with_context(|| format!("Invalid config in {filename}"))
+        } else {
+            Ok(Self::default())
+        }
+    }
+
     /// Read .r2 config file
     pub fn from_dot_r2() -> anyhow::Result<Self> {
-        Self::from_filename(".r2")
+        Self::from_optional_file(".r2")
     }
 
     pub fn from_global_dot_r2() -> anyhow::Result<Self> {
-        Self::from_filename("~/.r2").or_else(|_| Self::from_filename("~/.config/.r2"))
+        if Self::file_exists("~/.r2") {
+            Self::from_optional_file("~/.r2")
+        } else {
+            Self::from_optional_file("~/.config/.r2")
+        }
     }
 
     pub fn from_dotenv() -> anyhow::Result<Self> {
-        Self::from_filename(".env")
+        Self::from_optional_file(".env")
     }
 
     pub fn from_env() -> anyhow::Result<Self> {
@@ -444,11 +1020,146 @@ impl R2D2Builder {
             bucket: get_from_env("R2_BUCKET").ok(),
             aws_access_key_id: get_from_env("R2_ACCESS_KEY_ID").ok(),
             aws_secret_access_key: get_from_env("R2_SECRET_ACCESS_KEY").ok(),
+            aws_session_token: get_from_env("R2_SESSION_TOKEN").ok(),
+            append_only: get_from_env("R2_APPEND_ONLY")
+                .ok()
+                .map(|value| parse_flag(&value)),
+            price_class_a: get_from_env("R2_PRICE_CLASS_A").ok(),
+            price_class_b: get_from_env("R2_PRICE_CLASS_B").ok(),
+            backend: get_from_env("R2_BACKEND")
+                .ok()
+                .map(|value| value.parse())
+                .transpose()?,
+            jurisdiction: get_from_env("R2_JURISDICTION")
+                .ok()
+                .map(|value| parse_jurisdiction(&value))
+                .transpose()?
+                .flatten(),
+            endpoint: get_from_env("R2_ENDPOINT").ok(),
+            strict_listing: get_from_env("R2_STRICT_LISTING")
+                .ok()
+                .map(|value| parse_flag(&value)),
+            api_url: get_from_env("R2_API_URL").ok(),
+            http_timeout: get_from_env("R2_HTTP_TIMEOUT")
+                .ok()
+                .map(|value| parse_duration(&value))
+                .transpose()
+                .map_err(|error| anyhow!("`R2_HTTP_TIMEOUT`: {error}"))?,
+            https_proxy: get_from_env("HTTPS_PROXY")
+                .or_else(|_| get_from_env("https_proxy"))
+                .ok(),
+            ca_bundle: get_from_env("R2_CA_BUNDLE").ok(),
         })
     }
 
+    /// Combine all config sources: .r2, then .env, then environment variables.
+    /// Missing files are skipped, but invalid values are errors (instead of dropping the whole source).
+    pub fn guess() -> anyhow::Result<Self> {
+        let settings_local = Self::from_dot_r2()?;
+        let settings_global = Self::from_global_dot_r2()?;
+        let settings_env =
+            Self::from_env().context("Invalid config in the environment variables")?;
+        let settings_dotenv = Self::from_dotenv()?;
+
+        // use & to overwrite
+        // use | to fill
+        Ok(settings_global & settings_env & settings_local & settings_dotenv)
+    }
+
+    /// Keep the shared settings (network, pricing, backend), drop everything tied to one account
+    fn without_credentials(self) -> Self {
+        Self {
+            account_id: None,
+            apikey: None,
+            bucket: None,
+            aws_access_key_id: None,
+            aws_secret_access_key: None,
+            aws_session_token: None,
+            ..self
+        }
+    }
+
+    /// Every `.r2`-style file in `R2_PROFILES_DIR` (by file name), filled up with the shared settings.
+    /// Without any profiles, the normal config is the only (`default`) profile.
+    pub fn profiles() -> anyhow::Result<Vec<(String, Self)>> {
+        let base = Self::guess()?;
+        let dir = get_from_env("R2_PROFILES_DIR").unwrap_or_else(|_| PROFILES_DIR.to_owned());
+        let dir = Path::new(&dir);
+        let dir = dir
+            .try_resolve()
+            .map_or_else(|_| dir.to_path_buf(), std::borrow::Cow::into_owned);
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_file())
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+
+        let mut profiles = Vec::new();
+        for path in paths {
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+
+            if name.starts_with('.') {
+                continue;
+            }
+
+            let profile = Self::from_path(&path)
+                .with_context(|| format!("Invalid profile {}", path.display()))?;
+            profiles.push((
+                name.to_owned(),
+                profile | base.clone().without_credentials(),
+            ));
+        }
+
+        if profiles.is_empty() {
+            profiles.push((String::from("default"), base));
+        }
+
+        Ok(profiles)
+    }
+
+    /// R2 operation prices (USD per million requests), falling back to the public pricing
+    pub fn pricing(&self) -> OperationPricing {
+        let default = OperationPricing::default();
+        let parse = |value: &Option<String>, fallback: f64| {
+            value
+                .as_ref()
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or(fallback)
+        };
+
+        OperationPricing {
+            class_a: parse(&self.price_class_a, default.class_a),
+            class_b: parse(&self.price_class_b, default.class_b),
+        }
+    }
+
+    const fn is_local(&self) -> bool {
+        matches!(&self.backend, Some(backend) if backend.is_local())
+    }
+
+    /// Only the account is missing, which can be looked up with the API token
+    const fn is_missing_account(&self) -> bool {
+        self.account_id.is_none() && self.apikey.is_some() && !self.is_local()
+    }
+
+    const fn has_s3_credentials(&self) -> bool {
+        self.aws_access_key_id.is_some() && self.aws_secret_access_key.is_some()
+    }
+
     const fn is_complete(&self) -> bool {
-        self.account_id.is_some() && self.apikey.is_some()
+        // a local backend doesn't talk to R2 at all,
+        // and S3 credentials (e.g. from `temp-creds`) are enough for the backend:
+        (self.account_id.is_some() && self.apikey.is_some())
+            || ((self.account_id.is_some() || self.endpoint.is_some()) && self.has_s3_credentials())
+            || self.is_local()
         // other fields are optional in R2D2
     }
 }
@@ -461,14 +1172,31 @@ impl TryFrom<R2D2Builder> for R2D2 {
             bail!("Incomplete config");
         }
 
+        let client = HttpClient::try_new(
+            value.http_timeout,
+            value.https_proxy.as_deref(),
+            value.ca_bundle.as_deref(),
+        )?;
+
         Ok(Self {
-            account_id: value
-                .account_id
-                .expect("Should be filled if value.is_complete"),
-            apikey: value.apikey.expect("Should be filled if value.is_complete"),
+            // empty for local backends and S3-only configs:
+            account_id: value.account_id.unwrap_or_default(),
+            apikey: value.apikey.unwrap_or_default(),
             aws_access_key_id: value.aws_access_key_id,
             aws_secret_access_key: value.aws_secret_access_key,
+            aws_session_token: value.aws_session_token,
             bucket: value.bucket,
+            append_only: value.append_only.unwrap_or_default(),
+            backend: value.backend.unwrap_or_default(),
+            jurisdiction: value.jurisdiction,
+            endpoint: value.endpoint,
+            strict_listing: value.strict_listing.unwrap_or_default(),
+            api_url: value.api_url,
+            http_timeout: value.http_timeout,
+            https_proxy: value.https_proxy,
+            ca_bundle: value.ca_bundle,
+            client,
+            lock_rules: Vec::new(),
         })
     }
 }
@@ -476,31 +1204,126 @@ impl TryFrom<R2D2Builder> for R2D2 {
 impl R2D2 {
     // low-level: config, setup stuff:
 
-    pub fn guess() -> anyhow::Result<Self> {
+    pub async fn guess() -> anyhow::Result<Self> {
         // .r2, then .env, then environment variables
+        let mut settings_combined =
+            R2D2Builder::guess().context(R2Error::config("The config could not be read"))?;
 
-        let settings_local = R2D2Builder::from_dot_r2().unwrap_or_default();
-        let settings_global = R2D2Builder::from_global_dot_r2().unwrap_or_default();
-        let settings_env = R2D2Builder::from_env().unwrap_or_default();
-        let settings_dotenv = R2D2Builder::from_dotenv().unwrap_or_default();
-
-        // use & to overwrite
-        // use | to fill
-        let settings_combined = settings_global & settings_env & settings_local & settings_dotenv;
+        if settings_combined.is_missing_account() {
+            let account = Self::discover_account(&settings_combined).await?;
+            settings_combined.account_id = Some(account.id);
+        }
 
         if settings_combined.is_complete() {
             settings_combined.try_into()
         } else {
-            bail!("No complete config could be found (tried .r2, .env, environment variables)")
+            Err(R2Error::config(
+                "No complete config could be found (tried .r2, .env, environment variables)",
+            )
+            .into())
+        }
+    }
+
+    /// Every account the API token of `settings` has access to
+    async fn list_accounts(settings: &R2D2Builder) -> anyhow::Result<Vec<AccountData>> {
+        // `/accounts` doesn't need an account id, so an empty one is fine here:
+        let lookup: Self = R2D2Builder {
+            account_id: Some(String::new()),
+            ..settings.clone()
+        }
+        .try_into()?;
+
+        lookup.accounts().await?.into_error().context(
+            "`R2_ACCOUNT_ID` is not set and the accounts of the API token could not be listed",
+        )
+    }
+
+    /// Without `R2_ACCOUNT_ID`, use the only account the API token has access to.
+    async fn discover_account(settings: &R2D2Builder) -> anyhow::Result<AccountData> {
+        let mut accounts = Self::list_accounts(settings).await?;
+
+        match accounts.len() {
+            0 => {
+                bail!("`R2_ACCOUNT_ID` is not set and the API token has no access to any account.")
+            },
+            1 => Ok(accounts.remove(0)),
+            _ => {
+                let choices = accounts
+                    .iter()
+                    .map(|account| format!("  {} ({})", account.id, account.name))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                bail!(
+                    "`R2_ACCOUNT_ID` is not set and the API token has access to multiple accounts. Set it to one of:\n{choices}"
+                )
+            },
+        }
+    }
+
+    /// One client per profile (see `R2D2Builder::profiles`), labeled with the profile name.
+    /// A profile without `R2_ACCOUNT_ID` stands for every account its API token has access to.
+    pub async fn all_profiles() -> anyhow::Result<Vec<(String, anyhow::Result<Self>)>> {
+        let mut clients = Vec::new();
+
+        for (name, settings) in R2D2Builder::profiles()? {
+            if !settings.is_missing_account() {
+                let client = if settings.is_complete() {
+                    settings.try_into()
+                } else {
+                    Err(R2Error::config(format!("Profile `{name}` has no complete config")).into())
+                };
+                clients.push((name, client));
+                continue;
+            }
+
+            match Self::list_accounts(&settings).await {
+                Ok(accounts) => {
+                    for account in accounts {
+                        let client = R2D2Builder {
+                            account_id: Some(account.id),
+                            ..settings.clone()
+                        }
+                        .try_into();
+                        clients.push((format!("{name}: {}", account.name), client));
+                    }
+                },
+                Err(error) => clients.push((name, Err(error))),
+            }
         }
+
+        Ok(clients)
+    }
+
+    pub fn account_id(&self) -> &str {
+        &self.account_id
+    }
+
+    /// False for S3-only configs, which can't use the Cloudflare API.
+    pub const fn has_api_key(&self) -> bool {
+        !self.apikey.is_empty()
+    }
+
+    pub fn jurisdiction(&self) -> Option<&str> {
+        self.jurisdiction.as_deref()
     }
 
+    pub fn endpoint(&self) -> Option<&str> {
+        self.endpoint.as_deref()
+    }
+
+    #[expect(
+        clippy::implicit_clone,
+        reason = "Lint added after this code was written"
+    )]
     pub fn bucket_or(
         &self,
         bucket: &Option<String>,
     ) -> anyhow::Result<String> {
         let Some(bucket) = bucket.as_ref().or(self.bucket.as_ref()) else {
-            bail!("Bucket (`R2_BUCKET`) required for this operation.");
+            return Err(
+                R2Error::config("Bucket (`R2_BUCKET`) required for this operation.").into(),
+            );
         };
 
         Ok(bucket.to_string())
@@ -527,13 +1350,117 @@ impl R2D2 {
     //     Ok(S3Client::new(&shared_config))
     // }
 
+    /// Load the lock rules of the bucket, so the restic backend skips deletes of locked packs
+    /// in `prune` and names the rule when a locked index or snapshot would be deleted.
+    pub async fn with_lock_rules(mut self) -> anyhow::Result<Self> {
+        // local backends have no lock rules and S3-only configs can't read them:
+        if self.backend.is_local() || self.apikey.is_empty() {
+            return Ok(self);
+        }
+
+        match self.lock_rules(None).await {
+            Ok(list) => self.lock_rules = list.enabled().cloned().collect(),
+            Err(error) if is_not_configured(&error) => {},
+            Err(error) => {
+                // R2 itself still refuses to delete locked objects:
+                eprintln!(
+                    "{} lock rules could not be read, deletes of locked objects will fail: {error}",
+                    "Warning:".yellow()
+                );
+            },
+        }
+
+        Ok(self)
+    }
+
+    /// Fill in missing S3 credentials from the R2 API token:
+    /// the token id is the access key and the SHA-256 of the token is the secret.
+    /// See: [https://developers.cloudflare.com/r2/api/tokens/#get-s3-api-credentials-from-an-api-token](https://developers.cloudflare.com/r2/api/tokens/#get-s3-api-credentials-from-an-api-token)
+    pub async fn with_s3_credentials(mut self) -> anyhow::Result<Self> {
+        // a derived secret only works with the derived key, so never mix them:
+        match (&self.aws_access_key_id, &self.aws_secret_access_key) {
+            _ if self.backend.is_local() => return Ok(self),
+            (Some(_), Some(_)) => return Ok(self),
+            (Some(_), None) => {
+                return Err(R2Error::config(
+                    "`R2_ACCESS_KEY_ID` is set but `R2_SECRET_ACCESS_KEY` is missing.",
+                )
+                .into());
+            },
+            (None, Some(_)) => {
+                return Err(R2Error::config(
+                    "`R2_SECRET_ACCESS_KEY` is set but `R2_ACCESS_KEY_ID` is missing.",
+                )
+                .into());
+            },
+            (None, None) => {},
+        }
+
+        let verification = self
+            .verify()
+            .await?
+            .into_error()
+            .context("Could not derive S3 credentials from `R2_API_KEY`")?;
+
+        if !verification.ok() {
+            bail!(
+                "Could not derive S3 credentials: `R2_API_KEY` is not active (status: {}). Set `R2_ACCESS_KEY_ID` and `R2_SECRET_ACCESS_KEY` instead.",
+                verification.status
+            );
+        }
+
+        let secret = format!("{:x}", Sha256::digest(self.apikey.as_bytes()));
+
+        self.aws_access_key_id = Some(verification.id);
+        self.aws_secret_access_key = Some(secret);
+
+        Ok(self)
+    }
+
+    /// Client for the S3 backend, only needed with a proxy or CA bundle.
+    /// `R2_HTTP_TIMEOUT` is left out: it limits whole requests, which would break large uploads.
+    fn s3_http_client(&self) -> anyhow::Result<Option<Client>> {
+        if self.https_proxy.is_none() && self.ca_bundle.is_none() {
+            return Ok(None);
+        }
+
+        let client = client_builder(self.https_proxy.as_deref(), self.ca_bundle.as_deref())?
+            .build()
+            .context("Could not set up the HTTP client for S3")?;
+
+        Ok(Some(client))
+    }
+
     pub fn into_opendal_backend(self) -> anyhow::Result<R2Backend> {
-        R2Backend::try_new(
-            self.account_id,
-            self.aws_access_key_id.unwrap_or_default(),
-            self.aws_secret_access_key.unwrap_or_default(),
-            self.bucket.unwrap_or_default(),
-        )
+        let backend = match &self.backend {
+            BackendKind::S3 => {
+                let (Some(key_id), Some(secret)) = (
+                    self.aws_access_key_id.clone(),
+                    self.aws_secret_access_key.clone(),
+                ) else {
+                    return Err(R2Error::config(
+                        "`R2_ACCESS_KEY_ID` and `R2_SECRET_ACCESS_KEY` are required (or call `with_s3_credentials` to derive them from `R2_API_KEY`).",
+                    )
+                    .into());
+                };
+
+                R2Backend::try_new(
+                    self.endpoint_url(),
+                    key_id,
+                    secret,
+                    self.aws_session_token.clone(),
+                    self.bucket.clone().unwrap_or_default(),
+                    self.s3_http_client()?,
+                )
+            },
+            BackendKind::Memory => R2Backend::try_new_memory(),
+            BackendKind::Fs(root) => R2Backend::try_new_fs(root),
+        }?;
+
+        Ok(backend
+            .append_only(self.append_only)
+            .strict(self.strict_listing)
+            .lock_rules(self.lock_rules))
     }
 
     pub fn into_opendal_operator(self) -> anyhow::Result<Operator> {
@@ -549,13 +1476,30 @@ impl R2D2 {
         let backends = backend.into_backends();
 
         let progress_bar = ProgressBar::default();
-        let repo = Repository::new_with_progress(&repo_opts, &backends, progress_bar)?;
+        let repo =
+            Repository::new_with_progress(&repo_opts, &backends, progress_bar).map_err(|e| {
+                anyhow::Error::new(e).context(R2Error::repository(
+                    "Could not set up the restic repository",
+                ))
+            })?;
 
         Ok(repo)
     }
 
     pub fn endpoint_url(&self) -> String {
-        format!("https://{}.r2.cloudflarestorage.com", &self.account_id,)
+        if let Some(endpoint) = &self.endpoint {
+            return endpoint.clone();
+        }
+
+        let jurisdiction = self
+            .jurisdiction
+            .as_ref()
+            .map_or_else(String::new, |jurisdiction| format!(".{jurisdiction}"));
+
+        format!(
+            "https://{}{jurisdiction}.r2.cloudflarestorage.com",
+            &self.account_id
+        )
     }
 
     pub fn build_url(
@@ -563,11 +1507,17 @@ impl R2D2 {
         endpoint: &str,
     ) -> Option<Url> {
         let mut endpoint = endpoint; // clone
+        let api_url = self
+            .api_url
+            .as_deref()
+            .unwrap_or(CLOUDFLARE_API)
+            .trim_end_matches('/');
+
         let base = if endpoint.starts_with('/') {
             endpoint = endpoint.strip_prefix("/").unwrap_or(endpoint);
-            Url::parse(CLOUDFLARE_API).ok()?
+            Url::parse(&format!("{api_url}/")).ok()?
         } else {
-            let base_url = format!("{}/accounts/{}/r2/", CLOUDFLARE_API, self.account_id);
+            let base_url = format!("{api_url}/accounts/{}/r2/", self.account_id);
             Url::parse(&base_url).ok()?
         };
 
@@ -579,22 +1529,38 @@ impl R2D2 {
         &self,
         endpoint: &str,
     ) -> Option<RequestBuilder> {
-        let client = Client::new();
         let url = self.build_url(endpoint)?.to_string();
-        let request = client.get(url);
+        let request = self.client.0.get(url);
 
         self.headers().map(|headers| request.headers(headers))
     }
 
-    // todo: request POST/PUT/...
+    pub fn request_post(
+        &self,
+        endpoint: &str,
+    ) -> Option<RequestBuilder> {
+        let url = self.build_url(endpoint)?.to_string();
+        let request = self.client.0.post(url);
+
+        self.headers().map(|headers| request.headers(headers))
+    }
+
+    pub fn request_put(
+        &self,
+        endpoint: &str,
+    ) -> Option<RequestBuilder> {
+        let url = self.build_url(endpoint)?.to_string();
+        let request = self.client.0.put(url);
+
+        self.headers().map(|headers| request.headers(headers))
+    }
 
     pub fn request_delete(
         &self,
         endpoint: &str,
     ) -> Option<RequestBuilder> {
-        let client = Client::new();
         let url = self.build_url(endpoint)?.to_string();
-        let request = client.delete(url);
+        let request = self.client.0.delete(url);
 
         self.headers().map(|headers| request.headers(headers))
     }
@@ -604,6 +1570,11 @@ impl R2D2 {
         let bearer = format!("Bearer {}", self.apikey);
         headers.insert("Authorization", bearer.parse().ok()?);
 
+        // buckets in a jurisdiction are invisible to the API without this header:
+        if let Some(jurisdiction) = &self.jurisdiction {
+            headers.insert("cf-r2-jurisdiction", jurisdiction.parse().ok()?);
+        }
+
         Some(headers)
     }
 
@@ -620,6 +1591,10 @@ impl R2D2 {
     //     Ok(Credentials::new(key_id, secret, None, None, "r2-d2"))
     // }
 
+    pub const fn is_append_only(&self) -> bool {
+        self.append_only
+    }
+
     pub fn set_bucket(
         &mut self,
         bucket: Option<String>,
@@ -627,6 +1602,15 @@ impl R2D2 {
         self.bucket = bucket;
     }
 
+    /// `eu`, `fedramp` or `default`
+    pub fn set_jurisdiction(
+        &mut self,
+        jurisdiction: &str,
+    ) -> anyhow::Result<()> {
+        self.jurisdiction = parse_jurisdiction(jurisdiction)?;
+        Ok(())
+    }
+
     // medium level (api endpoints):
 
     pub async fn verify(&self) -> anyhow::Result<ApiResponse<TokenVerifyData>> {
@@ -641,6 +1625,15 @@ impl R2D2 {
         api_to_python!(self, verify)
     }
 
+    /// Accounts the API token has access to
+    pub async fn accounts(&self) -> anyhow::Result<ApiResponse<Vec<AccountData>>> {
+        let Some(request) = self.request_get("/accounts") else {
+            bail!("Request for '{}' could not be set up.", "accounts");
+        };
+
+        request.send_and_parse().await
+    }
+
     pub async fn bucket(
         &self,
         bucket: Option<String>,
@@ -686,26 +1679,42 @@ impl R2D2 {
         Url::parse(&domain_with_schema).ok()
     }
 
-    pub async fn bucket_custom_domains(
+    /// All custom domains, including disabled ones and their SSL/ownership status
+    pub async fn bucket_custom_domain_list(
         &self,
         bucket: Option<String>,
-    ) -> anyhow::Result<Vec<String>> {
+    ) -> anyhow::Result<Vec<CustomBucketDomainData>> {
         let resp: CustomBucketDomainList =
             bucket_request!(self, bucket, "domains/custom")?.into_error()?;
 
-        Ok(resp
-            .domains
+        Ok(resp.domains)
+    }
+
+    pub async fn bucket_custom_domains(
+        &self,
+        bucket: Option<String>,
+    ) -> anyhow::Result<Vec<String>> {
+        let domains = self.bucket_custom_domain_list(bucket).await?;
+
+        Ok(domains
             .into_iter()
             .filter_map(|item| item.enabled.then_some(item.domain))
             .collect())
     }
 
+    /// The r2.dev domain, also when it's disabled
+    pub async fn bucket_managed_domain_data(
+        &self,
+        bucket: Option<String>,
+    ) -> anyhow::Result<ManagedBucketDomainData> {
+        bucket_request!(self, bucket, "domains/managed")?.into_error()
+    }
+
     pub async fn bucket_managed_domain(
         &self,
         bucket: Option<String>,
     ) -> anyhow::Result<String> {
-        let resp: ManagedBucketDomainData =
-            bucket_request!(self, bucket, "domains/managed")?.into_error()?;
+        let resp = self.bucket_managed_domain_data(bucket).await?;
 
         if !resp.enabled {
             bail!("Managed domain disabled!")
@@ -729,32 +1738,104 @@ impl R2D2 {
         api_to_python!(self, usage, bucket)
     }
 
-    pub async fn list(
+    /// Fetch a single page of buckets
+    pub async fn list_page(
         &self,
-        _options: Option<ListOptions>,
+        options: &ListOptions,
     ) -> anyhow::Result<ApiResponse<BucketResultData>> {
-        let Some(request) = self.request_get("buckets") else {
-            bail!("Request for '{}' could not be set up.", "usage");
+        let query = options.to_query();
+        let endpoint = if query.is_empty() {
+            String::from("buckets")
+        } else {
+            format!("buckets?{query}")
         };
 
-        // let options = options.unwrap_or_default();
-        // todo: use options for:
-        //  cursor (for pagination), direction, name_contains, order, per_page, start_after
+        let Some(request) = self.request_get(&endpoint) else {
+            bail!("Request for '{}' could not be set up.", "list");
+        };
 
         request.send_and_parse().await
     }
 
+    /// Fetch all buckets, following the `cursor` until the last page
+    pub async fn list(
+        &self,
+        options: Option<ListOptions>,
+    ) -> anyhow::Result<ApiResponse<BucketResultData>> {
+        let mut options = options.unwrap_or_default();
+        let mut buckets = Vec::new();
+
+        loop {
+            let mut page = self.list_page(&options).await?;
+
+            let cursor = page
+                .result_info
+                .as_ref()
+                .and_then(|info| info.cursor.clone())
+                .filter(|cursor| !cursor.is_empty());
+
+            match page.result.take() {
+                Some(data) if page.success => buckets.extend(data.buckets),
+                // pass errors on as-is:
+                _ => return Ok(page),
+            }
+
+            // stop when the API doesn't advance the cursor (instead of looping forever)
+            if cursor.is_none() || cursor == options.cursor {
+                page.result_info = Some(ResultInfo {
+                    count: Some(buckets.len() as u64),
+                    total_count: Some(buckets.len() as u64),
+                    ..Default::default()
+                });
+                page.result = Some(BucketResultData { buckets });
+                return Ok(page);
+            }
+
+            options.cursor = cursor;
+        }
+    }
+
     /// List buckets
     pub async fn list_py(
         &self,
         options: Option<ListOptions>,
     ) -> PyResult<Vec<BucketData>> {
-        // todo: cursor (for pagination), direction, name_contains, order, per_page, start_after
         let data = api_to_python!(self, list, options)?;
 
         Ok(data.buckets)
     }
 
+    pub async fn create_bucket(
+        &self,
+        bucket: &str,
+        options: Option<CreateBucketOptions>,
+    ) -> anyhow::Result<ApiResponse<BucketData>> {
+        let options = options.unwrap_or_default();
+
+        // the jurisdiction is passed as header, so use a copy with that jurisdiction:
+        let mut r2 = self.clone();
+        if let Some(jurisdiction) = &options.jurisdiction {
+            r2.set_jurisdiction(jurisdiction)?;
+        }
+
+        let Some(request) = r2.request_post("buckets") else {
+            bail!("Request for '{}' could not be set up.", "create_bucket");
+        };
+
+        request
+            .json(&options.into_request(bucket))
+            .send_and_parse()
+            .await
+    }
+
+    pub async fn create_bucket_py(
+        &self,
+        bucket: &str,
+        options: Option<CreateBucketOptions>,
+    ) -> PyResult<BucketData> {
+        api_to_python!(self, create_bucket, bucket, options)
+    }
+
     pub async fn delete_bucket(
         &self,
         bucket: &str,
@@ -777,6 +1858,173 @@ impl R2D2 {
 
         Ok(data)
     }
+
+    /// See: [https://developers.cloudflare.com/r2/buckets/bucket-locks/](https://developers.cloudflare.com/r2/buckets/bucket-locks/)
+    pub async fn lock_rules(
+        &self,
+        bucket: Option<String>,
+    ) -> anyhow::Result<BucketLockRuleList> {
+        bucket_request!(self, bucket, "lock")?.into_error()
+    }
+
+    /// Replaces all lock rules of a bucket.
+    pub async fn set_lock_rules(
+        &self,
+        bucket: Option<String>,
+        rules: &BucketLockRuleList,
+    ) -> anyhow::Result<EmptyResponse> {
+        self.bucket_put(bucket, "lock", rules).await?.into_error()
+    }
+
+    /// See: [https://developers.cloudflare.com/r2/buckets/cors/](https://developers.cloudflare.com/r2/buckets/cors/)
+    pub async fn cors(
+        &self,
+        bucket: Option<String>,
+    ) -> anyhow::Result<BucketCorsPolicy> {
+        bucket_request!(self, bucket, "cors")?.into_error()
+    }
+
+    /// Replaces the CORS policy of a bucket.
+    pub async fn set_cors(
+        &self,
+        bucket: Option<String>,
+        policy: &BucketCorsPolicy,
+    ) -> anyhow::Result<EmptyResponse> {
+        self.bucket_put(bucket, "cors", policy).await?.into_error()
+    }
+
+    /// See: [https://developers.cloudflare.com/r2/buckets/object-lifecycles/](https://developers.cloudflare.com/r2/buckets/object-lifecycles/)
+    pub async fn lifecycle(
+        &self,
+        bucket: Option<String>,
+    ) -> anyhow::Result<BucketLifecyclePolicy> {
+        bucket_request!(self, bucket, "lifecycle")?.into_error()
+    }
+
+    /// Replaces all lifecycle rules of a bucket.
+    pub async fn set_lifecycle(
+        &self,
+        bucket: Option<String>,
+        policy: &BucketLifecyclePolicy,
+    ) -> anyhow::Result<EmptyResponse> {
+        self.bucket_put(bucket, "lifecycle", policy)
+            .await?
+            .into_error()
+    }
+
+    pub async fn delete_cors(
+        &self,
+        bucket: Option<String>,
+    ) -> anyhow::Result<EmptyResponse> {
+        self.bucket_delete(bucket, "cors").await?.into_error()
+    }
+
+    /// See: [https://developers.cloudflare.com/r2/buckets/public-buckets/](https://developers.cloudflare.com/r2/buckets/public-buckets/)
+    pub async fn attach_custom_domain(
+        &self,
+        bucket: Option<String>,
+        domain: &str,
+        zone_id: &str,
+    ) -> anyhow::Result<CustomBucketDomainData> {
+        let body = AttachCustomDomainRequest {
+            domain: domain.to_owned(),
+            zone_id: zone_id.to_owned(),
+            enabled: true,
+        };
+
+        self.bucket_post(bucket, "domains/custom", &body)
+            .await?
+            .into_error()
+    }
+
+    pub async fn detach_custom_domain(
+        &self,
+        bucket: Option<String>,
+        domain: &str,
+    ) -> anyhow::Result<EmptyResponse> {
+        self.bucket_delete(bucket, &format!("domains/custom/{domain}"))
+            .await?
+            .into_error()
+    }
+
+    /// Enable or disable public access via `<bucket id>.r2.dev`
+    pub async fn set_managed_domain(
+        &self,
+        bucket: Option<String>,
+        enabled: bool,
+    ) -> anyhow::Result<ManagedBucketDomainData> {
+        let body = BTreeMap::from([("enabled", enabled)]);
+
+        self.bucket_put(bucket, "domains/managed", &body)
+            .await?
+            .into_error()
+    }
+
+    /// Short-lived S3 credentials, derived from `R2_ACCESS_KEY_ID`.
+    /// See: [https://developers.cloudflare.com/api/resources/r2/subresources/temporary_credentials/](https://developers.cloudflare.com/api/resources/r2/subresources/temporary_credentials/)
+    pub async fn temp_credentials(
+        &self,
+        request: &TempCredentialsRequest,
+    ) -> anyhow::Result<TempCredentialsData> {
+        let Some(builder) = self.request_post("temp-access-credentials") else {
+            bail!("Request for '{}' could not be set up.", "temp_credentials");
+        };
+
+        builder.json(request).send_and_parse().await?.into_error()
+    }
+
+    pub fn access_key_id(&self) -> anyhow::Result<String> {
+        self.aws_access_key_id
+            .clone()
+            .ok_or_else(|| anyhow!("`R2_ACCESS_KEY_ID` required for this operation."))
+    }
+
+    /// POST `body` as json to `buckets/<bucket>/<path>`
+    async fn bucket_post<B: Serialize, T: serde::de::DeserializeOwned>(
+        &self,
+        bucket: Option<String>,
+        path: &str,
+        body: &B,
+    ) -> anyhow::Result<ApiResponse<T>> {
+        let bucket = self.bucket_or(&bucket)?;
+        let endpoint = format!("buckets/{bucket}/{path}");
+        let Some(request) = self.request_post(&endpoint) else {
+            bail!("Request for '{}' could not be set up.", endpoint);
+        };
+
+        request.json(body).send_and_parse().await
+    }
+
+    /// DELETE `buckets/<bucket>/<path>`
+    async fn bucket_delete<T: serde::de::DeserializeOwned>(
+        &self,
+        bucket: Option<String>,
+        path: &str,
+    ) -> anyhow::Result<ApiResponse<T>> {
+        let bucket = self.bucket_or(&bucket)?;
+        let endpoint = format!("buckets/{bucket}/{path}");
+        let Some(request) = self.request_delete(&endpoint) else {
+            bail!("Request for '{}' could not be set up.", endpoint);
+        };
+
+        request.send_and_parse().await
+    }
+
+    /// PUT `body` as json to `buckets/<bucket>/<path>`
+    async fn bucket_put<B: Serialize, T: serde::de::DeserializeOwned>(
+        &self,
+        bucket: Option<String>,
+        path: &str,
+        body: &B,
+    ) -> anyhow::Result<ApiResponse<T>> {
+        let bucket = self.bucket_or(&bucket)?;
+        let endpoint = format!("buckets/{bucket}/{path}");
+        let Some(request) = self.request_put(&endpoint) else {
+            bail!("Request for '{}' could not be set up.", endpoint);
+        };
+
+        request.json(body).send_and_parse().await
+    }
 }
 
 impl Display for R2D2 {
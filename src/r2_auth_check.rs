@@ -0,0 +1,314 @@
+use anyhow::{anyhow, bail};
+use opendal::Operator;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tabled::Tabled;
+
+use crate::commands::list::ListOptions;
+use crate::r2::{BucketCorsPolicy, BucketLifecyclePolicy, R2D2};
+use crate::r2_error::is_not_configured;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Pass,
+    Fail,
+    Skipped,
+    /// Not tested, e.g. because testing would change the bucket
+    Unknown,
+}
+
+#[derive(Tabled, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub check: String,
+    #[tabled(display = "display_outcome")]
+    pub result: Outcome,
+    pub detail: String,
+}
+
+#[expect(
+    clippy::trivially_copy_pass_by_ref,
+    reason = "tabled passes fields by reference."
+)]
+fn display_outcome(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Pass => "pass".green().to_string(),
+        Outcome::Fail => "fail".red().to_string(),
+        Outcome::Skipped => "skipped".yellow().to_string(),
+        Outcome::Unknown => "unknown".yellow().to_string(),
+    }
+}
+
+impl Check {
+    fn new(
+        check: &str,
+        result: anyhow::Result<String>,
+    ) -> Self {
+        let (result, detail) = match result {
+            Ok(detail) => (Outcome::Pass, detail),
+            Err(error) => (Outcome::Fail, format!("{error:#}")),
+        };
+
+        Self {
+            check: check.to_owned(),
+            result,
+            detail,
+        }
+    }
+
+    fn skipped(
+        check: &str,
+        reason: &str,
+    ) -> Self {
+        Self {
+            check: check.to_owned(),
+            result: Outcome::Skipped,
+            detail: reason.to_owned(),
+        }
+    }
+
+    fn unknown(
+        check: &str,
+        reason: &str,
+    ) -> Self {
+        Self {
+            result: Outcome::Unknown,
+            ..Self::skipped(check, reason)
+        }
+    }
+
+    pub fn failed(&self) -> bool {
+        self.result == Outcome::Fail
+    }
+
+    pub fn untested(&self) -> bool {
+        self.result == Outcome::Unknown
+    }
+}
+
+/// Reading a setting that was never configured still proves the permission.
+fn allow_missing<T>(result: anyhow::Result<T>) -> anyhow::Result<String> {
+    match result {
+        Ok(_) => Ok(String::from("readable")),
+        Err(error) if is_not_configured(&error) => Ok(String::from("readable (not configured)")),
+        Err(error) => Err(error),
+    }
+}
+
+async fn check_token(r2: &R2D2) -> anyhow::Result<String> {
+    let verification = r2.verify().await?.into_error()?;
+
+    if !verification.ok() {
+        bail!("token status is `{}`", verification.status);
+    }
+
+    Ok(format!("status `{}`", verification.status))
+}
+
+async fn check_list_buckets(r2: &R2D2) -> anyhow::Result<String> {
+    let options = ListOptions {
+        per_page: Some(1),
+        ..Default::default()
+    };
+
+    r2.list_page(&options).await?.into_error()?;
+
+    Ok(String::from("buckets can be listed"))
+}
+
+async fn check_usage(
+    r2: &R2D2,
+    bucket: &str,
+) -> anyhow::Result<String> {
+    let usage = r2.usage(Some(bucket.to_owned())).await?.into_error()?;
+
+    Ok(format!(
+        "{} object(s)",
+        usage.object_count.unwrap_or_else(|| String::from("0"))
+    ))
+}
+
+async fn check_domains(
+    r2: &R2D2,
+    bucket: &str,
+) -> anyhow::Result<String> {
+    let custom = r2
+        .bucket_custom_domain_list(Some(bucket.to_owned()))
+        .await?;
+    r2.bucket_managed_domain_data(Some(bucket.to_owned()))
+        .await?;
+
+    Ok(format!("{} custom domain(s)", custom.len()))
+}
+
+/// Writing back the r2.dev setting that was just read needs the permission, but changes nothing
+async fn check_manage_domains(
+    r2: &R2D2,
+    bucket: &str,
+) -> anyhow::Result<String> {
+    let managed = r2
+        .bucket_managed_domain_data(Some(bucket.to_owned()))
+        .await?;
+    r2.set_managed_domain(Some(bucket.to_owned()), managed.enabled)
+        .await?;
+
+    Ok(String::from("r2.dev setting written back unchanged"))
+}
+
+async fn check_manage_cors(
+    r2: &R2D2,
+    bucket: &str,
+    current: &anyhow::Result<BucketCorsPolicy>,
+) -> Check {
+    let check = "manage CORS";
+
+    match current {
+        Ok(policy) if !policy.rules.is_empty() => Check::new(
+            check,
+            r2.set_cors(Some(bucket.to_owned()), policy)
+                .await
+                .map(|_| String::from("policy written back unchanged")),
+        ),
+        Err(error) if !is_not_configured(error) => {
+            Check::skipped(check, "the policy could not be read")
+        },
+        _ => Check::unknown(check, "not tested, there is no policy to write back"),
+    }
+}
+
+async fn check_manage_lifecycle(
+    r2: &R2D2,
+    bucket: &str,
+    current: &anyhow::Result<BucketLifecyclePolicy>,
+) -> Check {
+    let check = "manage lifecycle";
+
+    match current {
+        Ok(policy) if !policy.rules.is_empty() => Check::new(
+            check,
+            r2.set_lifecycle(Some(bucket.to_owned()), policy)
+                .await
+                .map(|_| String::from("rules written back unchanged")),
+        ),
+        Err(error) if !is_not_configured(error) => {
+            Check::skipped(check, "the rules could not be read")
+        },
+        _ => Check::unknown(check, "not tested, there are no rules to write back"),
+    }
+}
+
+fn scratch_key() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+
+    format!(".r2-d2-auth-check-{nanos}")
+}
+
+/// Write, read back and delete a scratch object with the S3 credentials
+async fn check_s3(op: &Operator) -> [Check; 3] {
+    let key = scratch_key();
+    let payload = key.as_bytes().to_vec();
+
+    let write = op.write(&key, payload.clone()).await;
+    let write_ok = write.is_ok();
+
+    let write_check = Check::new(
+        "S3 write",
+        write.map(|_| format!("wrote `{key}`")).map_err(Into::into),
+    );
+
+    if !write_ok {
+        return [
+            write_check,
+            Check::skipped("S3 read", "nothing to read back"),
+            Check::skipped("S3 delete", "nothing to delete"),
+        ];
+    }
+
+    let read = match op.read(&key).await {
+        Ok(buffer) if buffer.to_vec() == payload => Ok(format!("read back `{key}`")),
+        Ok(_) => Err(anyhow!("`{key}` was read back with different content")),
+        Err(error) => Err(error.into()),
+    };
+
+    let delete = op
+        .delete(&key)
+        .await
+        .map(|()| format!("deleted `{key}`"))
+        .map_err(|error| anyhow!("{error} (remove `{key}` manually)"));
+
+    [
+        write_check,
+        Check::new("S3 read", read),
+        Check::new("S3 delete", delete),
+    ]
+}
+
+/// Find out what the API token and the S3 credentials are allowed to do
+pub async fn check_permissions(
+    r2: &R2D2,
+    bucket: Option<String>,
+) -> Vec<Check> {
+    let mut checks = vec![
+        Check::new("token status", check_token(r2).await),
+        Check::new("list buckets", check_list_buckets(r2).await),
+    ];
+
+    let Ok(bucket) = r2.bucket_or(&bucket) else {
+        let reason = "no bucket configured (`R2_BUCKET` or `--bucket`)";
+
+        for check in [
+            "read usage",
+            "read domains",
+            "read CORS",
+            "read lifecycle",
+            "manage domains",
+            "manage CORS",
+            "manage lifecycle",
+            "S3 write",
+            "S3 read",
+            "S3 delete",
+        ] {
+            checks.push(Check::skipped(check, reason));
+        }
+
+        return checks;
+    };
+
+    checks.push(Check::new("read usage", check_usage(r2, &bucket).await));
+    checks.push(Check::new("read domains", check_domains(r2, &bucket).await));
+
+    // the manage checks write back what was read, so nothing changes:
+    let cors = r2.cors(Some(bucket.clone())).await;
+    let lifecycle = r2.lifecycle(Some(bucket.clone())).await;
+    let manage = [
+        Check::new("manage domains", check_manage_domains(r2, &bucket).await),
+        check_manage_cors(r2, &bucket, &cors).await,
+        check_manage_lifecycle(r2, &bucket, &lifecycle).await,
+    ];
+
+    checks.push(Check::new("read CORS", allow_missing(cors)));
+    checks.push(Check::new("read lifecycle", allow_missing(lifecycle)));
+    checks.extend(manage);
+
+    let mut s3 = r2.clone();
+    s3.set_bucket(Some(bucket));
+
+    match s3
+        .with_s3_credentials()
+        .await
+        .and_then(R2D2::into_opendal_operator)
+    {
+        Ok(op) => checks.extend(check_s3(&op).await),
+        Err(error) => {
+            checks.push(Check::new("S3 write", Err(error)));
+            checks.push(Check::skipped("S3 read", "no S3 credentials"));
+            checks.push(Check::skipped("S3 delete", "no S3 credentials"));
+        },
+    }
+
+    checks
+}
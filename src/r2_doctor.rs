@@ -0,0 +1,135 @@
+use opendal::Operator;
+use serde::Serialize;
+use tabled::Tabled;
+
+use crate::r2::R2D2;
+
+/// Directories where restic stores files directly named after their id
+const ID_DIRS: [&str; 3] = ["keys", "snapshots", "index"];
+
+#[derive(Tabled, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub path: String,
+    pub problem: String,
+    pub suggested_fix: String,
+}
+
+impl Finding {
+    fn new(
+        path: &str,
+        problem: &str,
+        suggested_fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            path: path.to_owned(),
+            problem: problem.to_owned(),
+            suggested_fix: suggested_fix.into(),
+        }
+    }
+}
+
+fn is_id(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Check a single object key against the restic repository layout
+pub fn inspect_path(path: &str) -> Option<Finding> {
+    let parts: Vec<&str> = path.split('/').collect();
+    let name = parts.last().copied().unwrap_or_default();
+
+    if name.starts_with('.') {
+        return Some(Finding::new(
+            path,
+            "hidden file (e.g. macOS or editor metadata)",
+            "delete",
+        ));
+    }
+
+    match parts.as_slice() {
+        ["config"] => None,
+        [dir, name] if ID_DIRS.contains(dir) && is_id(name) => None,
+        [dir, _] if ID_DIRS.contains(dir) => {
+            Some(Finding::new(path, "file name is not a restic id", "delete"))
+        },
+        ["data", prefix, name] if is_id(name) && name.starts_with(prefix) => None,
+        ["data", _, name] | ["data", name] if is_id(name) => Some(Finding::new(
+            path,
+            "pack file in the wrong directory",
+            format!("move to `data/{}/{name}`", &name[..2]),
+        )),
+        ["data", ..] => Some(Finding::new(path, "not a pack file", "delete")),
+        ["locks", ..] => Some(Finding::new(
+            path,
+            "stale restic lock",
+            "delete if no restic process is using the repository",
+        )),
+        _ => Some(Finding::new(
+            path,
+            "not part of a restic repository",
+            "delete, or move it to another bucket",
+        )),
+    }
+}
+
+async fn diagnose_with_opendal(op: Operator) -> anyhow::Result<Vec<Finding>> {
+    let entries = op.list_with("").recursive(true).await?;
+
+    Ok(entries
+        .iter()
+        .filter(|entry| entry.metadata().is_file())
+        .filter_map(|entry| inspect_path(entry.path()))
+        .collect())
+}
+
+/// List every object in the repository bucket that restic doesn't know about
+pub async fn diagnose(r2: &R2D2) -> anyhow::Result<Vec<Finding>> {
+    let op = r2
+        .clone()
+        .with_s3_credentials()
+        .await?
+        .into_opendal_operator()?;
+
+    diagnose_with_opendal(op).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "ab34567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef";
+
+    #[test]
+    fn repository_files_are_accepted() {
+        assert_eq!(inspect_path("config"), None);
+        assert_eq!(inspect_path(&format!("data/ab/{ID}")), None);
+        assert_eq!(inspect_path(&format!("snapshots/{ID}")), None);
+    }
+
+    #[test]
+    fn misplaced_packs_are_moved() {
+        let finding = inspect_path(&format!("data/{ID}")).expect("a finding");
+        assert_eq!(finding.suggested_fix, format!("move to `data/ab/{ID}`"));
+
+        let finding = inspect_path(&format!("data/cd/{ID}")).expect("a finding");
+        assert_eq!(finding.suggested_fix, format!("move to `data/ab/{ID}`"));
+    }
+
+    #[test]
+    fn other_files_are_flagged() {
+        let finding = inspect_path("data/ab/.DS_Store").expect("a finding");
+        assert_eq!(
+            finding.problem,
+            "hidden file (e.g. macOS or editor metadata)"
+        );
+        assert_eq!(finding.suggested_fix, "delete");
+
+        assert_eq!(
+            inspect_path("index/notes.txt").map(|finding| finding.problem),
+            Some(String::from("file name is not a restic id"))
+        );
+        assert_eq!(
+            inspect_path("backup.tar.gz").map(|finding| finding.problem),
+            Some(String::from("not part of a restic repository"))
+        );
+    }
+}
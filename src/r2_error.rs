@@ -0,0 +1,321 @@
+use pyo3::{PyErr, PyTypeInfo, Python};
+use std::fmt::{Display, Formatter};
+
+/// Exceptions raised to Python, all deriving from `r2_d2.R2Error`
+pub mod exceptions {
+    use pyo3::create_exception;
+    use pyo3::exceptions::PyRuntimeError;
+
+    // `RuntimeError` as base keeps existing `except RuntimeError` handlers working:
+    create_exception!(r2_d2, R2Error, PyRuntimeError);
+    create_exception!(r2_d2, AuthenticationError, R2Error);
+    create_exception!(r2_d2, NotFound, R2Error);
+    create_exception!(r2_d2, BucketNotFound, NotFound);
+    create_exception!(r2_d2, BucketNotEmpty, R2Error);
+    create_exception!(r2_d2, RateLimited, R2Error);
+    create_exception!(r2_d2, ConfigError, R2Error);
+    create_exception!(r2_d2, RepositoryError, R2Error);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorKind {
+    /// Invalid, expired or insufficiently scoped credentials
+    Auth,
+    /// The bucket itself doesn't exist (code 10006)
+    BucketNotFound,
+    /// Any other 404, e.g. a bucket setting that was never configured
+    NotFound,
+    /// E.g. a bucket that already exists
+    Conflict,
+    RateLimited,
+    BucketNotEmpty,
+    /// The API could not be reached at all
+    Network,
+    /// Missing or invalid settings on our side
+    Config,
+    /// The restic repository could not be set up or opened
+    Repository,
+    /// Any other error reported by the API
+    Api,
+}
+
+impl ErrorKind {
+    /// CLI exit code, so scripts can tell e.g. a missing bucket from a network outage
+    pub const fn exit_code(self) -> i32 {
+        match self {
+            Self::Api => 1,
+            // 2 is used by clap for invalid arguments
+            Self::Config => 3,
+            Self::Auth => 4,
+            Self::BucketNotFound => 5,
+            Self::Conflict => 6,
+            Self::BucketNotEmpty => 7,
+            Self::RateLimited => 8,
+            Self::Network => 9,
+            Self::Repository => 10,
+            Self::NotFound => 11,
+        }
+    }
+
+    const fn from_status(status: u16) -> Self {
+        match status {
+            401 | 403 => Self::Auth,
+            404 => Self::NotFound,
+            409 => Self::Conflict,
+            429 => Self::RateLimited,
+            _ => Self::Api,
+        }
+    }
+
+    const fn hint(self) -> Option<&'static str> {
+        match self {
+            Self::Auth => {
+                Some("Check `R2_API_KEY` and its R2 permissions (see `r2-d2 auth --check`).")
+            },
+            Self::BucketNotFound => {
+                Some("Check the bucket name and `R2_JURISDICTION` (see `r2-d2 overview`).")
+            },
+            Self::RateLimited => Some("Too many requests, try again in a few minutes."),
+            Self::BucketNotEmpty => Some("Empty the bucket first with `r2-d2 wipe`."),
+            Self::Network => {
+                Some("Check your connection, `HTTPS_PROXY`, `R2_CA_BUNDLE` and `R2_HTTP_TIMEOUT`.")
+            },
+            Self::NotFound | Self::Conflict | Self::Config | Self::Repository | Self::Api => None,
+        }
+    }
+}
+
+/// Cloudflare error codes that need a more specific kind or hint than the HTTP status gives.
+/// See: [https://developers.cloudflare.com/r2/api/error-codes/](https://developers.cloudflare.com/r2/api/error-codes/)
+const KNOWN_CODES: [(i32, ErrorKind, Option<&str>); 5] = [
+    (
+        9109,
+        ErrorKind::Auth,
+        Some("The API token is invalid or expired."),
+    ),
+    (10000, ErrorKind::Auth, None),
+    // `NoSuchBucket`, unlike other 404s (e.g. a missing CORS policy):
+    (10006, ErrorKind::BucketNotFound, None),
+    (10008, ErrorKind::BucketNotEmpty, None),
+    (
+        10042,
+        ErrorKind::Config,
+        Some("R2 is not enabled for this account yet, enable it in the Cloudflare dashboard."),
+    ),
+];
+
+fn known_code(code: Option<i32>) -> Option<&'static (i32, ErrorKind, Option<&'static str>)> {
+    code.and_then(|code| KNOWN_CODES.iter().find(|(known, ..)| *known == code))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct R2Error {
+    pub kind: ErrorKind,
+    /// Cloudflare `ApiError.code`
+    pub code: Option<i32>,
+    /// HTTP status of the API response
+    pub status: Option<u16>,
+    pub message: String,
+}
+
+impl R2Error {
+    pub fn new(
+        kind: ErrorKind,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind,
+            code: None,
+            status: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn config(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Config, message)
+    }
+
+    pub fn network(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Network, message)
+    }
+
+    pub fn repository(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Repository, message)
+    }
+
+    /// Classify an error of the Cloudflare API by its code, falling back to the HTTP status
+    pub fn from_api(
+        code: Option<i32>,
+        status: Option<u16>,
+        message: impl Into<String>,
+    ) -> Self {
+        let message = message.into();
+
+        let kind = if let Some((_, kind, _)) = known_code(code) {
+            *kind
+        } else if message.to_lowercase().contains("not empty") {
+            ErrorKind::BucketNotEmpty
+        } else {
+            status.map_or(ErrorKind::Api, ErrorKind::from_status)
+        };
+
+        Self {
+            kind,
+            code,
+            status,
+            message,
+        }
+    }
+
+    pub fn hint(&self) -> Option<&'static str> {
+        known_code(self.code)
+            .and_then(|(_, _, hint)| *hint)
+            .or_else(|| self.kind.hint())
+    }
+
+    pub const fn exit_code(&self) -> i32 {
+        self.kind.exit_code()
+    }
+
+    /// The matching `r2_d2` exception, with `code`, `hint` and `status` attributes
+    pub fn to_py_err(
+        &self,
+        message: String,
+    ) -> PyErr {
+        let (code, hint, status) = (self.code, self.hint(), self.status);
+
+        match self.kind {
+            ErrorKind::Auth => {
+                new_py_err::<exceptions::AuthenticationError>(message, code, hint, status)
+            },
+            ErrorKind::BucketNotFound => {
+                new_py_err::<exceptions::BucketNotFound>(message, code, hint, status)
+            },
+            ErrorKind::NotFound => new_py_err::<exceptions::NotFound>(message, code, hint, status),
+            ErrorKind::BucketNotEmpty => {
+                new_py_err::<exceptions::BucketNotEmpty>(message, code, hint, status)
+            },
+            ErrorKind::RateLimited => {
+                new_py_err::<exceptions::RateLimited>(message, code, hint, status)
+            },
+            ErrorKind::Config => new_py_err::<exceptions::ConfigError>(message, code, hint, status),
+            ErrorKind::Repository => {
+                new_py_err::<exceptions::RepositoryError>(message, code, hint, status)
+            },
+            ErrorKind::Conflict | ErrorKind::Network | ErrorKind::Api => {
+                new_py_err::<exceptions::R2Error>(message, code, hint, status)
+            },
+        }
+    }
+}
+
+fn new_py_err<T: PyTypeInfo>(
+    message: String,
+    code: Option<i32>,
+    hint: Option<&str>,
+    status: Option<u16>,
+) -> PyErr {
+    Python::with_gil(|py| {
+        let error = PyErr::new::<T, _>(message);
+        let value = error.value(py);
+
+        // setting attributes on a fresh exception instance can't really fail:
+        let _ = value.setattr("code", code);
+        let _ = value.setattr("hint", hint);
+        let _ = value.setattr("status", status);
+
+        error
+    })
+}
+
+/// Raise any error as (a subclass of) `r2_d2.R2Error`
+pub fn to_py_err(
+    error: &anyhow::Error,
+    message: String,
+) -> PyErr {
+    match error.downcast_ref::<R2Error>() {
+        Some(r2_error) => r2_error.to_py_err(message),
+        None => new_py_err::<exceptions::R2Error>(message, None, None, None),
+    }
+}
+
+impl Display for R2Error {
+    fn fmt(
+        &self,
+        f: &mut Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(f, "{}", self.message.trim_end())?;
+
+        if let Some(code) = self.code {
+            write!(f, " (code {code})")?;
+        }
+
+        if let Some(hint) = self.hint() {
+            write!(f, "\nHint: {hint}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for R2Error {}
+
+/// A bucket setting (CORS, lifecycle, lock rules) that was never configured, as opposed to a failed request
+pub fn is_not_configured(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<R2Error>()
+        .is_some_and(|r2_error| r2_error.kind == ErrorKind::NotFound)
+}
+
+/// Exit code for any error, `1` unless it is (or has the context of) an `R2Error`
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    error
+        .downcast_ref::<R2Error>()
+        .map_or(1, R2Error::exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_bucket_is_not_a_generic_404() {
+        let bucket = R2Error::from_api(
+            Some(10006),
+            Some(404),
+            "The specified bucket does not exist.",
+        );
+        assert_eq!(bucket.kind, ErrorKind::BucketNotFound);
+        assert_eq!(bucket.exit_code(), 5);
+        assert!(!is_not_configured(&bucket.into()));
+
+        for code in [Some(10059), None] {
+            let missing =
+                R2Error::from_api(code, Some(404), "The CORS configuration does not exist.");
+            assert_eq!(missing.kind, ErrorKind::NotFound);
+            assert_eq!(missing.exit_code(), 11);
+            assert!(is_not_configured(&missing.into()));
+        }
+    }
+
+    #[test]
+    fn known_codes_win_over_the_status() {
+        assert_eq!(
+            R2Error::from_api(Some(9109), Some(400), "Invalid access token").kind,
+            ErrorKind::Auth
+        );
+        assert_eq!(
+            R2Error::from_api(
+                Some(10008),
+                Some(409),
+                "The bucket you tried to delete is not empty"
+            )
+            .kind,
+            ErrorKind::BucketNotEmpty
+        );
+        assert_eq!(
+            R2Error::from_api(None, Some(429), "Too many requests").kind,
+            ErrorKind::RateLimited
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use anyhow::bail;
 use opendal::Operator;
 
 use crate::r2::R2D2;
@@ -10,7 +11,15 @@ async fn empty_repo_with_opendal(op: Operator) -> anyhow::Result<()> {
 }
 
 pub async fn empty_repo(r2: &R2D2) -> anyhow::Result<()> {
-    let op = r2.clone().into_opendal_operator()?;
+    if r2.is_append_only() {
+        bail!("Refusing to empty the repository in append-only mode (`R2_APPEND_ONLY`).");
+    }
+
+    let op = r2
+        .clone()
+        .with_s3_credentials()
+        .await?
+        .into_opendal_operator()?;
 
     empty_repo_with_opendal(op).await
 }
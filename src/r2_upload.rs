@@ -97,9 +97,14 @@ pub async fn upload_file(
 
     let bucket = r2_op.bucket.clone();
 
-    let op = r2_op.into_opendal_operator()?;
+    let op = r2_op.with_s3_credentials().await?.into_opendal_operator()?;
     let key = upload_file_with_opendal(op, &file_path).await?;
 
+    // S3-only configs can't look up the bucket domain:
+    if !r2.has_api_key() {
+        return Ok(String::new());
+    }
+
     // bucket domain + key = public url
     let url = r2
         .bucket_domain(bucket)
@@ -111,5 +116,5 @@ pub async fn upload_file(
                 .unwrap_or_default()
         });
 
-    Ok(dbg!(url))
+    Ok(url)
 }
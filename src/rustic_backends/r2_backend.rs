@@ -3,70 +3,253 @@
     reason = "Rustic Errors expect formatting like that and may include extra variables not known at this point."
 )]
 
+use crate::metrics::MetricsLayer;
+use crate::r2::BucketLockRule;
+use anyhow::bail;
 use bytes::Bytes;
-use opendal::Operator;
-use opendal::services::S3 as S3Builder;
+use chrono::Utc;
+use opendal::layers::HttpClientLayer;
+use opendal::raw::HttpClient;
+use opendal::services::{Fs as FsBuilder, Memory as MemoryBuilder, S3 as S3Builder};
+use opendal::{Builder, Operator};
 use rustic_core::{
     ErrorKind, FileType, Id, ReadBackend, RepositoryBackends, RusticError, RusticResult,
     WriteBackend,
 };
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use std::sync::Arc;
 use tokio::runtime::Handle;
 use tokio::runtime::Runtime;
 use tokio::task;
 use typed_path::UnixPathBuf;
 
+/// Where the repository lives (`R2_BACKEND`): `s3` (default), `memory` or `fs:/path`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[serde(into = "String", try_from = "String")]
+pub enum BackendKind {
+    #[default]
+    S3,
+    Memory,
+    Fs(String),
+}
+
+impl BackendKind {
+    /// Local backends don't need an R2 account or API key.
+    pub const fn is_local(&self) -> bool {
+        !matches!(self, Self::S3)
+    }
+}
+
+impl FromStr for BackendKind {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+
+        if let Some(root) = value.strip_prefix("fs:") {
+            if root.is_empty() {
+                bail!("`R2_BACKEND=fs:` requires a path (e.g. `fs:/srv/restic`).");
+            }
+            return Ok(Self::Fs(root.to_owned()));
+        }
+
+        match value.to_lowercase().as_str() {
+            "" | "s3" | "r2" => Ok(Self::S3),
+            "memory" => Ok(Self::Memory),
+            _ => bail!("Unknown backend `{value}` (expected `s3`, `memory` or `fs:/path`)."),
+        }
+    }
+}
+
+impl Display for BackendKind {
+    fn fmt(
+        &self,
+        f: &mut Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::S3 => write!(f, "s3"),
+            Self::Memory => write!(f, "memory"),
+            Self::Fs(root) => write!(f, "fs:{root}"),
+        }
+    }
+}
+
+impl From<BackendKind> for String {
+    fn from(val: BackendKind) -> Self {
+        val.to_string()
+    }
+}
+
+impl TryFrom<String> for BackendKind {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
 /// Uses opendal async instead of blocking
 #[derive(Clone, Debug)]
 pub struct R2Backend {
-    account_id: String,
+    location: String,
     // key_id: String,
     // secret: String,
     // bucket: String,
     operator: Operator,
+    append_only: bool,
+    strict: bool,
+    /// Enabled lock rules of the bucket, deletes of locked packs are skipped (others fail)
+    lock_rules: Vec<BucketLockRule>,
 }
 
 impl R2Backend {
     fn s3_builder(
-        account_id: &str,
+        endpoint: &str,
         key_id: &str,
         secret: &str,
+        session_token: Option<&str>,
         bucket: &str,
     ) -> S3Builder {
-        S3Builder::default()
+        let builder = S3Builder::default()
             // set the storage bucket for OpenDAL
             .root("/")
             .region("auto")
-            .endpoint(&format!("https://{account_id}.r2.cloudflarestorage.com"))
+            .endpoint(endpoint)
             .access_key_id(key_id)
             .secret_access_key(secret)
-            .bucket(bucket)
+            .bucket(bucket);
+
+        match session_token {
+            Some(token) => builder.session_token(token),
+            None => builder,
+        }
     }
     // fn as_s3_builder(&self) -> S3Builder {
-    //     Self::s3_builder(&self.account_id, &self.key_id, &self.secret, &self.bucket)
+    //     Self::s3_builder(&self.endpoint, &self.key_id, &self.secret, &self.bucket)
     // }
 
+    /// `endpoint` is the full S3 url, see `R2D2::endpoint_url`
     #[expect(
         clippy::needless_pass_by_value,
-        reason = "We have to consume the R2D2 object anyway (for account id) so it's fine."
+        reason = "We have to consume the R2D2 object anyway (for the endpoint) so it's fine."
     )]
     pub fn try_new(
-        account_id: String,
+        endpoint: String,
         key_id: String,
         secret: String,
+        session_token: Option<String>,
         bucket: String,
+        http_client: Option<reqwest::Client>,
     ) -> anyhow::Result<Self> {
-        let builder = Self::s3_builder(&account_id, &key_id, &secret, &bucket);
+        let builder = Self::s3_builder(
+            &endpoint,
+            &key_id,
+            &secret,
+            session_token.as_deref(),
+            &bucket,
+        );
+
+        let mut operator = Operator::new(builder)?.layer(MetricsLayer).finish();
 
+        // e.g. a proxy or extra CA certificates:
+        if let Some(client) = http_client {
+            operator = operator.layer(HttpClientLayer::new(HttpClient::with(client)));
+        }
+
+        Ok(Self::from_operator(operator, endpoint))
+    }
+
+    /// Keeps the repository in memory (for tests); it's gone when the last clone is dropped.
+    pub fn try_new_memory() -> anyhow::Result<Self> {
+        Self::from_builder(MemoryBuilder::default(), String::from("memory"))
+    }
+
+    /// Stores the repository in a local directory, using the same layout as in the bucket.
+    pub fn try_new_fs(root: &str) -> anyhow::Result<Self> {
+        Self::from_builder(FsBuilder::default().root(root), format!("fs:{root}"))
+    }
+
+    /// Local backends aren't billed, so unlike `try_new` they skip the `MetricsLayer`.
+    fn from_builder(
+        builder: impl Builder,
+        location: String,
+    ) -> anyhow::Result<Self> {
         let async_op: Operator = Operator::new(builder)?.finish();
 
-        Ok(Self {
-            account_id,
+        Ok(Self::from_operator(async_op, location))
+    }
+
+    const fn from_operator(
+        operator: Operator,
+        location: String,
+    ) -> Self {
+        Self {
+            location,
             // key_id,
             // secret,
             // bucket,
-            operator: async_op,
-        })
+            operator,
+            append_only: false,
+            strict: false,
+            lock_rules: Vec::new(),
+        }
+    }
+
+    /// In append-only mode, deletes are refused.
+    /// rustic doesn't write `locks/`, so there is nothing that still needs removing.
+    #[must_use]
+    pub const fn append_only(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.append_only = enabled;
+        self
+    }
+
+    /// In strict mode, listing fails on files that aren't part of the restic repository
+    /// instead of skipping them.
+    #[must_use]
+    pub const fn strict(
+        mut self,
+        enabled: bool,
+    ) -> Self {
+        self.strict = enabled;
+        self
+    }
+
+    /// Locked packs can't be deleted yet, so `prune` keeps them (and can remove them on a later run);
+    /// deleting any other locked file fails instead.
+    #[must_use]
+    pub fn lock_rules(
+        mut self,
+        rules: Vec<BucketLockRule>,
+    ) -> Self {
+        self.lock_rules = rules;
+        self
+    }
+
+    /// The lock rule that still protects `path`, if any
+    async fn locked_by(
+        &self,
+        path: &str,
+    ) -> Option<&BucketLockRule> {
+        if self.lock_rules.is_empty() {
+            return None;
+        }
+
+        let modified = self
+            .operator
+            .stat(path)
+            .await
+            .ok()
+            .and_then(|metadata| metadata.last_modified());
+        let now = Utc::now();
+
+        self.lock_rules
+            .iter()
+            .find(|rule| rule.locks(path, modified, now))
     }
 
     pub fn into_operator(self) -> Operator {
@@ -77,6 +260,48 @@ impl R2Backend {
         RepositoryBackends::new(Arc::new(self), None)
     }
 
+    fn parse_entry(
+        e: &opendal::Entry,
+        length: u64,
+    ) -> RusticResult<(Id, u32)> {
+        Ok((
+            e.name().parse()?,
+            length.try_into().map_err(|err| {
+                RusticError::with_source(
+                    ErrorKind::Internal,
+                    "Parsing content length `{length}` failed",
+                    err,
+                )
+                .attach_context("length", length.to_string())
+            })?,
+        ))
+    }
+
+    /// The `fs` and `memory` listers leave out the content length, so stat those entries.
+    /// rustic compares these sizes with the index (e.g. in `prune` and `check`).
+    async fn entry_length(
+        &self,
+        e: &opendal::Entry,
+    ) -> RusticResult<u64> {
+        let length = e.metadata().content_length();
+        if length > 0 {
+            return Ok(length);
+        }
+
+        self.operator
+            .stat(e.path())
+            .await
+            .map(|metadata| metadata.content_length())
+            .map_err(|err| {
+                RusticError::with_source(
+                    ErrorKind::Backend,
+                    "Getting the size of `{path}` failed in the backend.",
+                    err,
+                )
+                .attach_context("path", e.path().to_owned())
+            })
+    }
+
     // code from `https://github.com/rustic-rs/rustic_core/blob/13587a2d5fe3b708544b76c3a9539a6906356ecb/crates/backend/src/opendal.rs`
     // but using non-blocking operator (since we're already in tokio)
 
@@ -110,7 +335,7 @@ impl R2Backend {
         }
 
         let path = tpe.dirname().to_string() + "/";
-        Ok(self
+        let listed: Vec<opendal::Entry> = self
             .operator
             .list_with(&path)
             .recursive(true)
@@ -121,34 +346,45 @@ impl R2Backend {
                     "Listing all files of `{type}` in directory `{path}` and their sizes failed in the backend. Please check if the given path is correct.",
                     err,
                 )
-                    .attach_context("path", path)
+                    .attach_context("path", path.clone())
                     .attach_context("type", tpe.to_string())
             )?
             .into_iter()
             .filter(|e| e.metadata().is_file())
-            .map(|e| -> RusticResult<(Id, u32)> {
-                Ok((
-                    e.name().parse()?,
-                    e.metadata()
-                        .content_length()
-                        .try_into()
-                        .map_err(|err|
-                            RusticError::with_source(
-                                ErrorKind::Internal,
-                                "Parsing content length `{length}` failed",
-                                err,
-                            )
-                                .attach_context("length", e.metadata().content_length().to_string())
-                        )?,
-                ))
-            })
-            .inspect(|r| {
-                if let Err(err) = r {
-                    eprintln!("Error while listing files: {}", err.display_log());
-                }
-            })
-            .filter_map(RusticResult::ok)
-            .collect())
+            .collect();
+
+        let mut entries: Vec<(String, RusticResult<(Id, u32)>)> = Vec::with_capacity(listed.len());
+        for e in &listed {
+            let length = self.entry_length(e).await?;
+            entries.push((e.path().to_owned(), Self::parse_entry(e, length)));
+        }
+
+        let mut unexpected = Vec::new();
+        let mut files = Vec::new();
+
+        for (file, result) in entries {
+            match result {
+                Ok(item) => files.push(item),
+                Err(err) => {
+                    if !self.strict {
+                        eprintln!("Error while listing files: {}", err.display_log());
+                    }
+                    unexpected.push(file);
+                },
+            }
+        }
+
+        if self.strict && !unexpected.is_empty() {
+            return Err(RusticError::new(
+                ErrorKind::Backend,
+                "Found {count} unexpected file(s) in `{path}`: {files}. Run `r2-d2 doctor` for suggested fixes.",
+            )
+                .attach_context("count", unexpected.len().to_string())
+                .attach_context("path", path)
+                .attach_context("files", unexpected.join(", ")));
+        }
+
+        Ok(files)
     }
 
     fn path(
@@ -250,6 +486,38 @@ impl R2Backend {
         _cacheable: bool,
     ) -> RusticResult<()> {
         let filename = self.path(tpe, id);
+
+        if self.append_only {
+            return Err(RusticError::new(
+                ErrorKind::Backend,
+                "Refusing to delete `{path}`: the backend is in append-only mode (`R2_APPEND_ONLY`).",
+            )
+            .attach_context("path", filename)
+            .attach_context("type", tpe.to_string())
+            .attach_context("id", id.to_string()));
+        }
+
+        if let Some(rule) = self.locked_by(&filename).await {
+            // a skipped pack is only unreferenced (not missing), so the repository stays consistent;
+            // a kept index or snapshot would still be in use, so the caller has to know:
+            if tpe != FileType::Pack {
+                return Err(RusticError::new(
+                    ErrorKind::Backend,
+                    "Refusing to delete `{path}`: it is locked by rule `{rule}` ({condition}).",
+                )
+                .attach_context("path", filename)
+                .attach_context("rule", rule.id.clone())
+                .attach_context("condition", rule.condition.to_string())
+                .attach_context("type", tpe.to_string()));
+            }
+
+            eprintln!(
+                "Keeping `{filename}`: locked by rule `{}` ({}).",
+                rule.id, rule.condition
+            );
+            return Ok(());
+        }
+
         self.operator.delete(&filename).await.map_err(|err| {
             RusticError::with_source(
                 ErrorKind::Backend,
@@ -285,7 +553,7 @@ macro_rules! block_on_in_place {
 
 impl ReadBackend for R2Backend {
     fn location(&self) -> String {
-        format!("https://{}.r2.cloudflarestorage.com", self.account_id)
+        self.location.clone()
     }
 
     // Forward to async functions using existing runtime
@@ -351,3 +619,59 @@ impl WriteBackend for R2Backend {
         block_on_in_place!(self.remove_async(tpe, id, cacheable).await)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r2::BucketLockCondition;
+
+    fn id() -> Id {
+        "0".repeat(64).parse().expect("valid id")
+    }
+
+    #[test]
+    fn memory_round_trip() -> RusticResult<()> {
+        let backend = R2Backend::try_new_memory().expect("memory backend");
+        let buf = Bytes::from_static(b"pack contents");
+
+        backend.write_bytes(FileType::Pack, &id(), false, buf.clone())?;
+
+        assert_eq!(backend.read_full(FileType::Pack, &id())?, buf);
+        assert_eq!(
+            backend.read_partial(FileType::Pack, &id(), false, 5, 8)?,
+            buf.slice(5..13)
+        );
+        assert_eq!(
+            backend.list_with_size(FileType::Pack)?,
+            vec![(id(), u32::try_from(buf.len()).expect("small buffer"))]
+        );
+
+        backend.remove(FileType::Pack, &id(), false)?;
+        assert!(backend.list_with_size(FileType::Pack)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn locked_files_are_kept() -> RusticResult<()> {
+        let backend = R2Backend::try_new_memory()
+            .expect("memory backend")
+            .lock_rules(vec![BucketLockRule {
+                id: String::from("forever"),
+                enabled: true,
+                prefix: None,
+                condition: BucketLockCondition::Indefinite,
+            }]);
+
+        backend.write_bytes(FileType::Pack, &id(), false, Bytes::from_static(b"pack"))?;
+        backend.remove(FileType::Pack, &id(), false)?;
+        assert_eq!(backend.list_with_size(FileType::Pack)?.len(), 1);
+
+        // only packs are skipped quietly, e.g. `forget` must not report a kept snapshot as removed:
+        backend.write_bytes(FileType::Snapshot, &id(), false, Bytes::from_static(b"{}"))?;
+        assert!(backend.remove(FileType::Snapshot, &id(), false).is_err());
+        assert_eq!(backend.list_with_size(FileType::Snapshot)?.len(), 1);
+
+        Ok(())
+    }
+}
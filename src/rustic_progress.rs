@@ -203,6 +203,10 @@ impl Progress for ProgressType {
         clippy::cast_sign_loss,
         reason = "This percentage will always be positive"
     )]
+    #[expect(
+        clippy::useless_let_if_seq,
+        reason = "Lint added after this code was written"
+    )]
     fn inc(
         &self,
         inc: u64,